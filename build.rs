@@ -1,7 +1,9 @@
 fn main() {
-    prost_build::compile_protos(
-        &["protobuf/requests.proto", "protobuf/results.proto"],
-        &["protobuf"],
-    )
-    .expect("Failed to build protobuf.")
+    prost_build::Config::new()
+        .boxed(".Scraping.Requests.ScrapingRequest.Source.generic") // Keeps the request variants similar in size.
+        .compile_protos(
+            &["protobuf/requests.proto", "protobuf/results.proto"],
+            &["protobuf"],
+        )
+        .expect("Failed to build protobuf.")
 }
@@ -0,0 +1,15 @@
+{
+  "token": "c1-5f1e2d3c4b5a69788796a5b4c3d2e1f0",
+  "note": null,
+  "attributes": {},
+  "original_total_price": 0,
+  "total_price": 0,
+  "total_discount": 0,
+  "total_weight": 0.0,
+  "item_count": 0,
+  "items": [],
+  "requires_shipping": false,
+  "currency": "JPY",
+  "items_subtotal_price": 0,
+  "cart_level_discount_applications": []
+}
@@ -0,0 +1,38 @@
+{
+  "id": 8012345678901,
+  "title": "Canvas Tote",
+  "handle": "canvas-tote",
+  "vendor": "Loom & Co",
+  "type": "Bags",
+  "tags": ["bags", "sale"],
+  "price": 2400,
+  "price_min": 2400,
+  "price_max": 2400,
+  "available": true,
+  "compare_at_price": 3200,
+  "options": [{ "name": "Title", "position": 1, "values": ["Default Title"] }],
+  "images": ["//loomandco.example/cdn/shop/files/canvas-tote.jpg?v=1712345678"],
+  "featured_image": "//loomandco.example/cdn/shop/files/canvas-tote.jpg?v=1712345678",
+  "variants": [
+    {
+      "id": 44012345678901,
+      "title": "Default Title",
+      "option1": "Default Title",
+      "option2": null,
+      "option3": null,
+      "sku": "TOTE-NAT",
+      "requires_shipping": true,
+      "taxable": true,
+      "featured_image": null,
+      "available": true,
+      "name": "Canvas Tote",
+      "public_title": null,
+      "options": ["Default Title"],
+      "price": 2400,
+      "weight": 350,
+      "compare_at_price": 3200,
+      "inventory_management": "shopify",
+      "barcode": ""
+    }
+  ]
+}
@@ -0,0 +1,38 @@
+{
+  "id": 7012345670003,
+  "title": "抹茶セット",
+  "handle": "matcha-set",
+  "vendor": "Chaya",
+  "type": "Tea",
+  "tags": [],
+  "price": 480000,
+  "price_min": 480000,
+  "price_max": 480000,
+  "available": true,
+  "compare_at_price": 550000,
+  "options": [{ "name": "Title", "position": 1, "values": ["Default Title"] }],
+  "images": ["//chaya.example/cdn/shop/files/matcha-set.jpg?v=1701234567"],
+  "featured_image": "//chaya.example/cdn/shop/files/matcha-set.jpg?v=1701234567",
+  "variants": [
+    {
+      "id": 42012345670031,
+      "title": "Default Title",
+      "option1": "Default Title",
+      "option2": null,
+      "option3": null,
+      "sku": "MATCHA-SET",
+      "requires_shipping": true,
+      "taxable": true,
+      "featured_image": null,
+      "available": true,
+      "name": "抹茶セット",
+      "public_title": null,
+      "options": ["Default Title"],
+      "price": 480000,
+      "weight": 800,
+      "compare_at_price": 550000,
+      "inventory_management": "shopify",
+      "barcode": ""
+    }
+  ]
+}
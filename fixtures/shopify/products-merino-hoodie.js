@@ -0,0 +1,73 @@
+{
+  "id": 8012345679902,
+  "title": "Merino Hoodie",
+  "handle": "merino-hoodie",
+  "vendor": "Loom & Co",
+  "type": "Hoodies",
+  "tags": ["merino"],
+  "price": 12900,
+  "price_min": 12900,
+  "price_max": 12900,
+  "available": true,
+  "compare_at_price": null,
+  "options": [
+    { "name": "Size", "position": 1, "values": ["S", "M"] },
+    { "name": "Color", "position": 2, "values": ["Black"] }
+  ],
+  "images": [
+    "//loomandco.example/cdn/shop/files/merino-hoodie.jpg?v=1712345679",
+    "//loomandco.example/cdn/shop/files/merino-hoodie-black.jpg?v=1712345679"
+  ],
+  "featured_image": "//loomandco.example/cdn/shop/files/merino-hoodie.jpg?v=1712345679",
+  "variants": [
+    {
+      "id": 44012345679911,
+      "title": "S / Black",
+      "option1": "S",
+      "option2": "Black",
+      "option3": null,
+      "sku": "HOOD-S-BLK",
+      "requires_shipping": true,
+      "taxable": true,
+      "featured_image": {
+        "id": 36012345679911,
+        "product_id": 8012345679902,
+        "position": 2,
+        "alt": "Merino Hoodie in black",
+        "width": 1600,
+        "height": 1600,
+        "src": "//loomandco.example/cdn/shop/files/merino-hoodie-black.jpg?v=1712345679",
+        "variant_ids": [44012345679911, 44012345679912]
+      },
+      "available": true,
+      "name": "Merino Hoodie - S / Black",
+      "public_title": "S / Black",
+      "options": ["S", "Black"],
+      "price": 12900,
+      "weight": 600,
+      "compare_at_price": null,
+      "inventory_management": "shopify",
+      "barcode": ""
+    },
+    {
+      "id": 44012345679912,
+      "title": "M / Black",
+      "option1": "M",
+      "option2": "Black",
+      "option3": null,
+      "sku": "",
+      "requires_shipping": true,
+      "taxable": true,
+      "featured_image": null,
+      "available": false,
+      "name": "Merino Hoodie - M / Black",
+      "public_title": "M / Black",
+      "options": ["M", "Black"],
+      "price": 12900,
+      "weight": 620,
+      "compare_at_price": null,
+      "inventory_management": "shopify",
+      "barcode": ""
+    }
+  ]
+}
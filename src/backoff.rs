@@ -0,0 +1,44 @@
+use std::time::Duration;
+
+use rand::Rng;
+
+use crate::config::env_or;
+
+/// Exponential backoff with full jitter, used between publish attempts.
+pub(crate) struct BackoffConfig {
+    pub(crate) max_attempts: u32,
+    base: Duration,
+    max: Duration,
+    retry_after_max: Duration,
+}
+
+impl BackoffConfig {
+    pub(crate) fn from_env() -> Self {
+        let max_attempts: u32 = env_or("PUBLISH_MAX_ATTEMPTS", 5);
+        let base_ms: u64 = env_or("PUBLISH_BACKOFF_BASE_MS", 500);
+        let max_ms: u64 = env_or("PUBLISH_BACKOFF_MAX_MS", 30_000);
+        let retry_after_max_ms: u64 = env_or("PUBLISH_RETRY_AFTER_MAX_MS", 60_000);
+
+        BackoffConfig {
+            max_attempts: max_attempts.max(1),
+            base: Duration::from_millis(base_ms),
+            max: Duration::from_millis(max_ms),
+            retry_after_max: Duration::from_millis(retry_after_max_ms),
+        }
+    }
+
+    /// Returns how long to wait after the given (1-based) failed attempt.
+    /// A `Retry-After` hint from the server takes precedence over the computed delay, capped at
+    /// `PUBLISH_RETRY_AFTER_MAX_MS` since publishing waits on it.
+    pub(crate) fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        if let Some(retry_after) = retry_after {
+            return retry_after.min(self.retry_after_max);
+        }
+
+        // Full jitter: a uniformly random delay between zero and the exponential ceiling
+        let exponent = attempt.saturating_sub(1).min(31);
+        let ceiling = self.base.saturating_mul(1 << exponent).min(self.max);
+        let ceiling_ms = ceiling.as_millis() as u64;
+        Duration::from_millis(rand::thread_rng().gen_range(0..=ceiling_ms))
+    }
+}
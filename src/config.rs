@@ -0,0 +1,15 @@
+use std::{fmt::Debug, str::FromStr};
+
+/// Reads an optional env variable, falling back to the default when it is not set.
+pub(crate) fn env_or<T>(key: &str, default: T) -> T
+where
+    T: FromStr,
+    T::Err: Debug,
+{
+    match std::env::var(key) {
+        Ok(value) => value
+            .parse()
+            .unwrap_or_else(|e| panic!("Invalid {} env variable: {:?}", key, e)),
+        Err(_) => default,
+    }
+}
@@ -0,0 +1,78 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::config::env_or;
+
+/// Tracks domains that have served a block page, so that they are left alone for a while.
+/// Requests to the same domain also share a small number of permits, so that requests queued
+/// behind a blocked one see the cool-down instead of hitting the domain again.
+pub(crate) struct DomainCooldowns {
+    blocked_until: Mutex<HashMap<String, Instant>>,
+    permits: Mutex<HashMap<String, Arc<Semaphore>>>,
+    cooldown: Duration,
+    max_concurrency: usize,
+}
+
+impl DomainCooldowns {
+    pub(crate) fn new(cooldown: Duration, max_concurrency: usize) -> Self {
+        DomainCooldowns {
+            blocked_until: Mutex::new(HashMap::new()),
+            permits: Mutex::new(HashMap::new()),
+            cooldown,
+            max_concurrency: max_concurrency.max(1),
+        }
+    }
+
+    pub(crate) fn from_env() -> Self {
+        let cooldown_secs: u64 = env_or("BLOCKED_COOLDOWN_SECS", 900);
+        let max_concurrency: usize = env_or("DOMAIN_MAX_CONCURRENCY", 4);
+        DomainCooldowns::new(Duration::from_secs(cooldown_secs), max_concurrency)
+    }
+
+    /// Waits for one of the domain's permits. The permit is released when dropped.
+    pub(crate) async fn acquire(&self, domain: &str) -> OwnedSemaphorePermit {
+        let semaphore = self
+            .permits
+            .lock()
+            .expect("Poisoned cooldown lock.")
+            .entry(domain.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(self.max_concurrency)))
+            .clone();
+
+        semaphore
+            .acquire_owned()
+            .await
+            .expect("Semaphore is never closed.")
+    }
+
+    /// Returns how long the domain remains in cool-down, if it is in one.
+    pub(crate) fn remaining(&self, domain: &str) -> Option<Duration> {
+        let mut blocked_until = self.blocked_until.lock().expect("Poisoned cooldown lock.");
+        let until = *blocked_until.get(domain)?;
+
+        let remaining = until.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            blocked_until.remove(domain);
+            return None;
+        }
+        Some(remaining)
+    }
+
+    /// Puts the domain into cool-down.
+    pub(crate) fn block(&self, domain: &str) {
+        println!(
+            "Blocked by {}, cooling down for {}s.",
+            domain,
+            self.cooldown.as_secs()
+        );
+        self.blocked_until
+            .lock()
+            .expect("Poisoned cooldown lock.")
+            .insert(domain.to_string(), Instant::now() + self.cooldown);
+    }
+}
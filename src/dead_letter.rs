@@ -0,0 +1,152 @@
+use std::{path::PathBuf, sync::Arc};
+
+use tokio::{fs::OpenOptions, io::AsyncWriteExt, sync::Mutex};
+
+use crate::{
+    errors::ScraperError,
+    postal::PostalSender,
+    scraping::{json_results::ScrapingResultJson, results::ScrapingResult},
+};
+
+/// A result that could not be published, along with why it failed.
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+pub(crate) struct DeadLetterRecord {
+    pub(crate) result: ScrapingResultJson,
+    pub(crate) failure_reason: String,
+    pub(crate) attempts: u32,
+    pub(crate) dead_lettered_at: u64,
+}
+
+/// Local JSONL file holding results that exhausted their publish retries.
+#[derive(Clone)]
+pub(crate) struct DeadLetterStore {
+    path: PathBuf,
+    lock: Arc<Mutex<()>>, // Serializes access to the file.
+}
+
+impl DeadLetterStore {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        DeadLetterStore {
+            path,
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub(crate) fn from_env() -> Self {
+        let path =
+            std::env::var("DEAD_LETTER_PATH").unwrap_or_else(|_| "dead_letters.jsonl".to_string());
+        DeadLetterStore::new(PathBuf::from(path))
+    }
+
+    pub(crate) async fn append(&self, records: &[DeadLetterRecord]) -> Result<(), ScraperError> {
+        let _guard = self.lock.lock().await;
+        self.append_unlocked(records).await
+    }
+
+    pub(crate) async fn read_all(&self) -> Result<Vec<DeadLetterRecord>, ScraperError> {
+        let _guard = self.lock.lock().await;
+        self.read_all_unlocked().await
+    }
+
+    /// Hands every dead-lettered result back to the postal service.
+    /// Records that could not be re-driven are kept in the file.
+    /// Returns the number of records that were re-driven.
+    pub(crate) async fn redrive(
+        &self,
+        postal_sender: &PostalSender,
+    ) -> Result<usize, ScraperError> {
+        // Moving the records into the outbox
+        // Each record stays in the file until it is persisted in the outbox, so that a crash in
+        // between can only re-drive a record twice, never lose it.
+        let (entries, failure) = {
+            let _guard = self.lock.lock().await;
+            let mut records = self.read_all_unlocked().await?.into_iter();
+            let mut entries = Vec::new();
+            let mut remaining = Vec::new();
+            let mut failure = None;
+            while let Some(record) = records.next() {
+                let result = ScrapingResult::from(record.result.clone());
+                match postal_sender.persist(result).await {
+                    Ok(entry) => entries.push(entry),
+                    Err(e) => {
+                        remaining = std::iter::once(record).chain(records).collect();
+                        failure = Some(e);
+                        break;
+                    }
+                }
+            }
+
+            // Rewriting the file without the records now held by the outbox
+            self.rewrite_unlocked(&remaining).await?;
+            (entries, failure)
+        };
+
+        // Queuing the records once the lock is released
+        // The postal service appends to this file when a publish fails, and it may be waiting to do
+        // so while the channel is full. Records that cannot be queued are replayed on restart.
+        let record_count = entries.len();
+        for entry in entries {
+            postal_sender.queue(entry).await?;
+        }
+
+        match failure {
+            Some(e) => Err(e),
+            None => Ok(record_count),
+        }
+    }
+
+    async fn append_unlocked(&self, records: &[DeadLetterRecord]) -> Result<(), ScraperError> {
+        let lines = to_lines(records)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .map_err(ScraperError::storage)?;
+        file.write_all(lines.as_bytes())
+            .await
+            .map_err(ScraperError::storage)?;
+        file.sync_all().await.map_err(ScraperError::storage)
+    }
+
+    /// Replaces the contents of the file, through a temporary file so that it is never half written.
+    async fn rewrite_unlocked(&self, records: &[DeadLetterRecord]) -> Result<(), ScraperError> {
+        let lines = to_lines(records)?;
+        let temp_path = self.path.with_extension("tmp");
+        let mut file = tokio::fs::File::create(&temp_path)
+            .await
+            .map_err(ScraperError::storage)?;
+        file.write_all(lines.as_bytes())
+            .await
+            .map_err(ScraperError::storage)?;
+        file.sync_all().await.map_err(ScraperError::storage)?;
+        tokio::fs::rename(&temp_path, &self.path)
+            .await
+            .map_err(ScraperError::storage)
+    }
+
+    async fn read_all_unlocked(&self) -> Result<Vec<DeadLetterRecord>, ScraperError> {
+        let contents = match tokio::fs::read_to_string(&self.path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(ScraperError::storage(e)),
+        };
+
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(ScraperError::storage))
+            .collect()
+    }
+}
+
+/// Encodes the records as JSON lines.
+fn to_lines(records: &[DeadLetterRecord]) -> Result<String, ScraperError> {
+    let mut lines = String::new();
+    for record in records {
+        let line = serde_json::to_string(record).map_err(ScraperError::storage)?;
+        lines.push_str(&line);
+        lines.push('\n');
+    }
+    Ok(lines)
+}
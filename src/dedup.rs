@@ -0,0 +1,202 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{config::env_or, scraping_traits::Scraper};
+
+struct DedupEntries {
+    seen: HashMap<String, (Instant, u64)>, // Insertion time and generation of each key.
+    order: VecDeque<(String, Instant, u64)>, // Oldest first, used for eviction.
+    next_generation: u64, // Tells a key's current position apart from those it held before being forgotten.
+}
+
+/// Bounded record of recently seen keys, used to skip redelivered messages and repeated requests.
+/// Keys are forgotten once their TTL has elapsed, or when the store is full.
+pub(crate) struct DedupStore {
+    entries: Mutex<DedupEntries>,
+    capacity: usize,
+    ttl: Duration,
+}
+
+impl DedupStore {
+    pub(crate) fn new(capacity: usize, ttl: Duration) -> Self {
+        DedupStore {
+            entries: Mutex::new(DedupEntries {
+                seen: HashMap::new(),
+                order: VecDeque::new(),
+                next_generation: 0,
+            }),
+            capacity: capacity.max(1),
+            ttl,
+        }
+    }
+
+    pub(crate) fn from_env() -> Self {
+        let capacity: usize = env_or("DEDUP_CAPACITY", 10_000);
+        let ttl_secs: u64 = env_or("DEDUP_TTL_SECS", 3600);
+        DedupStore::new(capacity, Duration::from_secs(ttl_secs))
+    }
+
+    /// Records the key, returning `false` if it was already seen within the TTL.
+    pub(crate) fn insert(&self, key: &str) -> bool {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().expect("Poisoned dedup lock.");
+
+        // Evicting expired entries, and the oldest entries once the store is full
+        while let Some((oldest_key, inserted_at, generation)) = entries.order.front() {
+            let expired = now.duration_since(*inserted_at) >= self.ttl;
+            if !expired && entries.order.len() < self.capacity {
+                break;
+            }
+
+            // The key may have been forgotten and re-inserted since, in which case only the stale
+            // position is dropped
+            if entries.seen.get(oldest_key).map(|(_, g)| g) == Some(generation) {
+                let oldest_key = oldest_key.clone();
+                entries.seen.remove(&oldest_key);
+            }
+            entries.order.pop_front();
+        }
+
+        if let Some((inserted_at, _)) = entries.seen.get(key) {
+            if now.duration_since(*inserted_at) < self.ttl {
+                return false;
+            }
+        }
+
+        let generation = entries.next_generation;
+        entries.next_generation += 1;
+        entries.seen.insert(key.to_string(), (now, generation));
+        entries.order.push_back((key.to_string(), now, generation));
+        true
+    }
+
+    /// Forgets the key, so that it is accepted again.
+    /// Used when the work the key guarded did not complete, and is going to be redelivered.
+    pub(crate) fn forget(&self, key: &str) {
+        self.entries
+            .lock()
+            .expect("Poisoned dedup lock.")
+            .seen
+            .remove(key);
+    }
+
+    pub(crate) fn insert_message_id(&self, message_id: &str) -> bool {
+        self.insert(&message_key(message_id))
+    }
+
+    pub(crate) fn forget_message_id(&self, message_id: &str) {
+        self.forget(&message_key(message_id))
+    }
+
+    /// Keys recorded for the idempotency keys of these requests.
+    pub(crate) fn request_keys(
+        &self,
+        scraping_requests: &[Box<dyn Scraper + Send>],
+    ) -> Vec<String> {
+        scraping_requests
+            .iter()
+            .filter_map(|req| request_key(req.as_ref()))
+            .collect()
+    }
+
+    /// Drops the requests whose idempotency key has already been seen.
+    pub(crate) fn deduplicate_requests(
+        &self,
+        scraping_requests: Vec<Box<dyn Scraper + Send>>,
+    ) -> Vec<Box<dyn Scraper + Send>> {
+        scraping_requests
+            .into_iter()
+            .filter(|req| match request_key(req.as_ref()) {
+                Some(request_key) => {
+                    let inserted = self.insert(&request_key);
+                    if !inserted {
+                        println!("Skipping duplicate request {}.", req.get_unique_id());
+                    }
+                    inserted
+                }
+                None => true,
+            })
+            .collect()
+    }
+}
+
+fn message_key(message_id: &str) -> String {
+    format!("message:{}", message_id)
+}
+
+fn request_key(req: &(dyn Scraper + Send)) -> Option<String> {
+    let idempotency_key = req.get_idempotency_key()?;
+    Some(format!(
+        "request:{}:{}",
+        req.get_source_name(),
+        idempotency_key
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread::sleep, time::Duration};
+
+    use super::DedupStore;
+
+    #[test]
+    fn rejects_keys_seen_within_the_ttl() {
+        let store = DedupStore::new(10, Duration::from_secs(60));
+        assert!(store.insert("a"));
+        assert!(!store.insert("a"));
+        assert!(store.insert("b"));
+    }
+
+    #[test]
+    fn accepts_keys_again_once_their_ttl_has_elapsed() {
+        let store = DedupStore::new(10, Duration::from_millis(20));
+        assert!(store.insert("a"));
+        sleep(Duration::from_millis(30));
+        assert!(store.insert("a"));
+        assert!(!store.insert("a"));
+    }
+
+    #[test]
+    fn evicts_the_oldest_keys_once_full() {
+        let store = DedupStore::new(2, Duration::from_secs(60));
+        assert!(store.insert("a"));
+        assert!(store.insert("b"));
+        assert!(store.insert("c")); // Evicts "a"
+        assert!(!store.insert("c"));
+        assert!(store.insert("a"));
+    }
+
+    #[test]
+    fn accepts_forgotten_keys_again() {
+        let store = DedupStore::new(10, Duration::from_secs(60));
+        assert!(store.insert("a"));
+        store.forget("a");
+        assert!(store.insert("a"));
+        assert!(!store.insert("a"));
+    }
+
+    #[test]
+    fn evicting_a_stale_position_keeps_the_reinserted_key() {
+        let store = DedupStore::new(3, Duration::from_secs(60));
+        assert!(store.insert("a"));
+        store.forget("a");
+        assert!(store.insert("a")); // "a" now holds two positions, the first one stale
+        assert!(store.insert("b"));
+
+        // The store is full, so the stale position of "a" is evicted, leaving "a" itself recorded
+        assert!(!store.insert("a"));
+    }
+
+    #[test]
+    fn message_ids_and_request_keys_do_not_collide() {
+        let store = DedupStore::new(10, Duration::from_secs(60));
+        assert!(store.insert_message_id("1"));
+        assert!(store.insert("1"));
+        store.forget_message_id("1");
+        assert!(store.insert_message_id("1"));
+        assert!(!store.insert("1"));
+    }
+}
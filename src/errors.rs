@@ -1,9 +1,206 @@
-use std::{error::Error, fmt::Display};
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fmt::Display,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
+use reqwest::{header::RETRY_AFTER, Response, StatusCode};
 use scraper::error::SelectorErrorKind;
-use tokio::sync::mpsc::Receiver;
+use tokio::sync::mpsc::{Receiver, Sender};
 
-type BoxedErr = Box<dyn Error + Send>;
+use crate::{config::env_or, sinks::error_sink::ErrorSink};
+
+/// Underlying errors that are wrapped without being inspected further.
+pub type DynError = Box<dyn Error + Send + Sync>;
+
+/// Identifies what was being worked on when an error was raised.
+#[derive(Debug, Clone, Default)]
+pub struct ErrorContext {
+    pub source: Option<String>,
+    pub product_id: Option<String>,
+    pub url: Option<String>,
+}
+
+impl ErrorContext {
+    pub(crate) fn product(source: String, product_id: String) -> Self {
+        ErrorContext {
+            source: Some(source),
+            product_id: Some(product_id),
+            url: None,
+        }
+    }
+
+    pub(crate) fn url(url: impl Into<String>) -> Self {
+        ErrorContext {
+            url: Some(url.into()),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn with_url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+}
+
+impl Display for ErrorContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fields = [
+            ("source", &self.source),
+            ("product_id", &self.product_id),
+            ("url", &self.url),
+        ];
+        let formatted = fields
+            .iter()
+            .filter_map(|(name, value)| value.as_ref().map(|value| format!("{}={}", name, value)))
+            .collect::<Vec<_>>();
+        write!(f, "{}", formatted.join(" "))
+    }
+}
+
+/// Every error raised while receiving, scraping or publishing.
+/// Variants are kept coarse enough for the error handler to tell failure modes apart.
+#[derive(Debug, thiserror::Error)]
+pub enum ScraperError {
+    #[error("Failed to fetch after {attempts} attempt(s) [{context}]: {source}")]
+    Fetch {
+        context: ErrorContext,
+        source: reqwest::Error,
+        attempts: u32,
+    },
+    #[error("Unexpected HTTP status {status} after {attempts} attempt(s) [{context}]")]
+    HttpStatus {
+        context: ErrorContext,
+        status: u16,
+        attempts: u32,
+    },
+    #[error("Failed to parse {selector:?} [{context}]: {source}")]
+    Parse {
+        context: ErrorContext,
+        selector: String,
+        source: CssError,
+    },
+    #[error("Blocked by the target site: {reason} [{context}]")]
+    Blocked {
+        context: ErrorContext,
+        reason: String,
+    },
+    #[error("Invalid price format {raw:?} [{context}]")]
+    PriceFormat { context: ErrorContext, raw: String },
+    #[error("Invalid scraping request [{context}]: {reason}")]
+    InvalidRequest {
+        context: ErrorContext,
+        reason: String,
+    },
+    #[error("Failed to decode scraping requests: {source}")]
+    Decode { source: DynError },
+    #[error("Failed to publish results: {source}")]
+    Publish {
+        #[from]
+        source: PublishError,
+    },
+    #[error("Failed to authenticate: {source}")]
+    Auth { source: DynError },
+    #[error("Failed to access local storage: {source}")]
+    Storage { source: DynError },
+    #[error("Internal error: {reason}")]
+    Internal { reason: String },
+}
+
+impl ScraperError {
+    /// Responses rejected by `error_for_status` are reported as `HttpStatus`, anything else as `Fetch`.
+    pub(crate) fn from_request(context: ErrorContext, error: reqwest::Error) -> Self {
+        match error.status() {
+            Some(status) => ScraperError::HttpStatus {
+                context: match error.url() {
+                    Some(url) if context.url.is_none() => context.with_url(url.as_str()),
+                    _ => context,
+                },
+                status: status.as_u16(),
+                attempts: 1,
+            },
+            None => ScraperError::Fetch {
+                context,
+                source: error,
+                attempts: 1,
+            },
+        }
+    }
+
+    /// Records how many times the request was attempted before giving up.
+    pub(crate) fn with_attempts(mut self, attempts: u32) -> Self {
+        if let ScraperError::Fetch { attempts: a, .. }
+        | ScraperError::HttpStatus { attempts: a, .. } = &mut self
+        {
+            *a = attempts;
+        }
+        self
+    }
+
+    /// Number of attempts made before the error was raised.
+    pub fn attempts(&self) -> u32 {
+        match self {
+            ScraperError::Fetch { attempts, .. } | ScraperError::HttpStatus { attempts, .. } => {
+                *attempts
+            }
+            _ => 1,
+        }
+    }
+
+    pub(crate) fn decode(error: impl Into<DynError>) -> Self {
+        ScraperError::Decode {
+            source: error.into(),
+        }
+    }
+
+    pub(crate) fn auth(error: impl Into<DynError>) -> Self {
+        ScraperError::Auth {
+            source: error.into(),
+        }
+    }
+
+    pub(crate) fn storage(error: impl Into<DynError>) -> Self {
+        ScraperError::Storage {
+            source: error.into(),
+        }
+    }
+
+    pub(crate) fn internal(reason: impl Display) -> Self {
+        ScraperError::Internal {
+            reason: reason.to_string(),
+        }
+    }
+
+    /// Stable name of the variant, used to classify and aggregate errors.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ScraperError::Fetch { .. } => "fetch",
+            ScraperError::HttpStatus { .. } => "http_status",
+            ScraperError::Parse { .. } => "parse",
+            ScraperError::Blocked { .. } => "blocked",
+            ScraperError::PriceFormat { .. } => "price_format",
+            ScraperError::InvalidRequest { .. } => "invalid_request",
+            ScraperError::Decode { .. } => "decode",
+            ScraperError::Publish { .. } => "publish",
+            ScraperError::Auth { .. } => "auth",
+            ScraperError::Storage { .. } => "storage",
+            ScraperError::Internal { .. } => "internal",
+        }
+    }
+
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            ScraperError::Fetch { context, .. }
+            | ScraperError::HttpStatus { context, .. }
+            | ScraperError::Parse { context, .. }
+            | ScraperError::Blocked { context, .. }
+            | ScraperError::PriceFormat { context, .. }
+            | ScraperError::InvalidRequest { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct CssError {
@@ -33,20 +230,321 @@ impl From<SelectorErrorKind<'_>> for CssError {
     }
 }
 
-impl From<CssError> for Box<dyn Error + Send> {
-    fn from(value: CssError) -> Self {
-        Box::new(value) as Box<dyn Error + Send>
+/// Raised when a push request fails authentication.
+#[derive(Debug)]
+pub struct AuthError {
+    error_msg: String,
+}
+
+impl Display for AuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.error_msg)
+    }
+}
+
+impl Error for AuthError {}
+
+impl AuthError {
+    pub(crate) fn new(error_msg: &str) -> Self {
+        AuthError {
+            error_msg: error_msg.to_string(),
+        }
     }
 }
 
-pub(crate) async fn spawn_error_handler_service(mut errors_rx: Receiver<BoxedErr>) {
+impl From<jsonwebtoken::errors::Error> for AuthError {
+    fn from(value: jsonwebtoken::errors::Error) -> Self {
+        let error_msg = format!("Invalid token: {}", value);
+        AuthError { error_msg }
+    }
+}
+
+/// An error raised while publishing results to a sink.
+/// Carries whether the publish is worth retrying and any `Retry-After` hint from the server.
+#[derive(Debug)]
+pub struct PublishError {
+    error: DynError,
+    retryable: bool,
+    retry_after: Option<Duration>,
+}
+
+impl Display for PublishError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl Error for PublishError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.error.as_ref())
+    }
+}
+
+impl PublishError {
+    pub(crate) fn retryable(error: impl Into<DynError>) -> Self {
+        PublishError {
+            error: error.into(),
+            retryable: true,
+            retry_after: None,
+        }
+    }
+
+    pub(crate) fn permanent(error: impl Into<DynError>) -> Self {
+        PublishError {
+            error: error.into(),
+            retryable: false,
+            retry_after: None,
+        }
+    }
+
+    pub(crate) fn is_retryable(&self) -> bool {
+        self.retryable
+    }
+
+    pub(crate) fn retry_after(&self) -> Option<Duration> {
+        self.retry_after
+    }
+
+    /// Passes successful responses through. Only 429 and 5xx responses are considered retryable.
+    pub(crate) fn check_response(response: Response) -> Result<Response, PublishError> {
+        let status = response.status();
+        let retry_after = parse_retry_after(&response);
+
+        match response.error_for_status() {
+            Ok(response) => Ok(response),
+            Err(e) => Err(PublishError {
+                error: Box::new(e),
+                retryable: status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error(),
+                retry_after,
+            }),
+        }
+    }
+}
+
+/// `Retry-After` may either be a number of seconds or an HTTP date.
+fn parse_retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    match value.parse::<u64>() {
+        Ok(seconds) => Some(Duration::from_secs(seconds)),
+        Err(_) => {
+            let retry_at = httpdate::parse_http_date(value).ok()?;
+            Some(
+                retry_at
+                    .duration_since(std::time::SystemTime::now())
+                    .unwrap_or_default(),
+            )
+        }
+    }
+}
+
+impl From<reqwest::Error> for PublishError {
+    fn from(value: reqwest::Error) -> Self {
+        // Connection failures and timeouts are transient, anything else will fail again.
+        let retryable = value.is_connect() || value.is_timeout();
+        PublishError {
+            error: Box::new(value),
+            retryable,
+            retry_after: None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for PublishError {
+    fn from(value: serde_json::Error) -> Self {
+        PublishError::permanent(value)
+    }
+}
+
+impl From<std::io::Error> for PublishError {
+    fn from(value: std::io::Error) -> Self {
+        PublishError::retryable(value)
+    }
+}
+
+/// An error as forwarded to the error sink.
+#[derive(Debug, serde::Serialize)]
+pub(crate) struct ErrorRecord {
+    utc_timestamp: u64,
+    kind: &'static str,
+    source: Option<String>,
+    product_id: Option<String>,
+    url: Option<String>,
+    message: String,
+    suppressed: u64, // Identical errors suppressed since this error was last forwarded.
+}
+
+/// Occurrences of an identical error within the current rate limit window.
+struct RateLimitWindow {
+    started_at: Instant,
+    suppressed: u64,
+}
+
+/// Classifies errors, keeps per source and kind counts, and rate limits identical errors before
+/// they are forwarded to the error sink.
+struct ErrorPipeline {
+    counts: BTreeMap<(String, &'static str), u64>,
+    windows: HashMap<(&'static str, String), RateLimitWindow>,
+    rate_limit_window: Duration,
+    dropped: u64, // Records the error sink could not keep up with.
+}
+
+impl ErrorPipeline {
+    fn new(rate_limit_window: Duration) -> Self {
+        ErrorPipeline {
+            counts: BTreeMap::new(),
+            windows: HashMap::new(),
+            rate_limit_window,
+            dropped: 0,
+        }
+    }
+
+    /// Returns the record to forward, or `None` if the error was suppressed.
+    fn process(&mut self, error: ScraperError) -> Option<ErrorRecord> {
+        let kind = error.kind();
+        let context = error.context().cloned().unwrap_or_default();
+        let message = error.to_string();
+
+        // Aggregating counts per source and error kind
+        let source = context.source.clone().unwrap_or_else(|| "-".to_string());
+        *self.counts.entry((source, kind)).or_default() += 1;
+
+        // Suppressing identical errors within the rate limit window
+        let now = Instant::now();
+        let suppressed = match self.windows.get_mut(&(kind, message.clone())) {
+            Some(window) if now.duration_since(window.started_at) < self.rate_limit_window => {
+                window.suppressed += 1;
+                return None;
+            }
+            Some(window) => {
+                let suppressed = window.suppressed;
+                *window = RateLimitWindow {
+                    started_at: now,
+                    suppressed: 0,
+                };
+                suppressed
+            }
+            None => {
+                self.windows.insert(
+                    (kind, message.clone()),
+                    RateLimitWindow {
+                        started_at: now,
+                        suppressed: 0,
+                    },
+                );
+                0
+            }
+        };
+
+        Some(ErrorRecord {
+            utc_timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("Unable to retrieve system time.")
+                .as_secs(),
+            kind,
+            source: context.source,
+            product_id: context.product_id,
+            url: context.url,
+            message,
+            suppressed,
+        })
+    }
+
+    /// Logs the counts so far and forgets expired windows.
+    /// Suppressed errors in a forgotten window are still reflected in the counts.
+    fn summarize(&mut self) {
+        let now = Instant::now();
+        let rate_limit_window = self.rate_limit_window;
+        self.windows
+            .retain(|_, window| now.duration_since(window.started_at) < rate_limit_window);
+
+        if self.counts.is_empty() {
+            return;
+        }
+        let summary = self
+            .counts
+            .iter()
+            .map(|((source, kind), count)| format!("{}/{}={}", source, kind, count))
+            .collect::<Vec<_>>()
+            .join(" ");
+        eprintln!("Error counts: {}", summary);
+        if self.dropped > 0 {
+            eprintln!(
+                "Dropped {} error(s) that the error sink could not keep up with.",
+                self.dropped
+            );
+        }
+    }
+}
+
+/// Number of records waiting to be forwarded before new ones are dropped.
+const FORWARD_QUEUE_CAPACITY: usize = 1024;
+
+/// Largest number of records forwarded to the error sink at once.
+const MAX_RECORDS_PER_PUBLISH: usize = 100;
+
+/// Forwards records to the error sink in batches of whatever has queued up since the last publish.
+async fn forward_error_records(mut records_rx: Receiver<ErrorRecord>, error_sink: ErrorSink) {
+    while let Some(record) = records_rx.recv().await {
+        let mut records = vec![record];
+        while records.len() < MAX_RECORDS_PER_PUBLISH {
+            match records_rx.try_recv() {
+                Ok(record) => records.push(record),
+                Err(_) => break,
+            }
+        }
+
+        // Errors from the error sink cannot be sent back through the errors channel
+        if let Err(e) = error_sink.publish(&records).await {
+            eprintln!(
+                "Failed to forward {} error(s) to the error sink: {}",
+                records.len(),
+                e
+            );
+        }
+    }
+}
+
+pub(crate) async fn spawn_error_handler_service(
+    mut errors_rx: Receiver<ScraperError>,
+    errors_tx: Sender<ScraperError>,
+) {
+    // CONSTANTS
+    let rate_limit_secs: u64 = env_or("ERROR_RATE_LIMIT_SECS", 60);
+    let summary_interval_secs: u64 = env_or("ERROR_SUMMARY_INTERVAL_SECS", 300);
+    let sink_timeout_secs: u64 = env_or("ERROR_SINK_TIMEOUT_SECS", 10);
+
     // Logging the start of the error handler service
     println!("Starting error handler service...");
 
-    while let Some(error) = errors_rx.recv().await {
-        // TODO: Implement error handling
-        // Logging the error to the console for now
-        eprintln!("Error: {}", error);
+    // Creating the configured error sink
+    let client = reqwest::ClientBuilder::new()
+        .timeout(Duration::from_secs(sink_timeout_secs))
+        .build()
+        .expect("Failed to build reqwest client.");
+    let error_sink = ErrorSink::from_env(client, errors_tx);
+
+    // Forwarding records off the receive loop
+    // Producers wait on the errors channel, so a stalled sink must never hold up this loop.
+    let (records_tx, records_rx) = tokio::sync::mpsc::channel(FORWARD_QUEUE_CAPACITY);
+    tokio::task::spawn(forward_error_records(records_rx, error_sink));
+
+    let mut pipeline = ErrorPipeline::new(Duration::from_secs(rate_limit_secs));
+    let mut summary_interval =
+        tokio::time::interval(Duration::from_secs(summary_interval_secs.max(1)));
+
+    loop {
+        tokio::select! {
+            error = errors_rx.recv() => {
+                let Some(error) = error else { break };
+                if let Some(record) = pipeline.process(error) {
+                    if records_tx.try_send(record).is_err() {
+                        pipeline.dropped += 1;
+                    }
+                }
+            }
+            _ = summary_interval.tick() => pipeline.summarize(),
+        }
     }
 
     unreachable!("Error handler service has exited unexpectedly.")
@@ -1,5 +1,3 @@
-use std::error::Error;
-
 use actix_web::{
     guard::{Get, Post},
     web::{self, resource, route},
@@ -8,65 +6,195 @@ use actix_web::{
 use reqwest::ClientBuilder;
 
 use crate::{
-    errors::spawn_error_handler_service,
-    postal::spawn_postal_service,
-    scraping::results::ScrapingResult,
-    services::{hello_world, scraping_request_handler},
+    cooldown::DomainCooldowns,
+    dead_letter::DeadLetterStore,
+    dedup::DedupStore,
+    errors::{spawn_error_handler_service, ScraperError},
+    outbox::{Outbox, OutboxEntry},
+    postal::{spawn_postal_service, PostalSender},
+    pull_consumer::spawn_pull_consumer_service,
+    push_auth::{AdminAuthenticator, PushAuthenticator},
+    services::{
+        dead_letters_handler, hello_world, redrive_dead_letters_handler, scraping_request_handler,
+    },
+    sources::SourceRegistry,
 };
 
+pub(crate) mod backoff;
+pub(crate) mod config;
+pub(crate) mod cooldown;
+pub(crate) mod dead_letter;
+pub(crate) mod dedup;
 pub(crate) mod errors;
+pub(crate) mod outbox;
 pub(crate) mod postal;
+pub mod price;
 pub(crate) mod pubsub;
+pub(crate) mod pull_consumer;
+pub(crate) mod push_auth;
 pub mod scraping;
 pub mod scraping_traits;
 pub(crate) mod services;
+pub(crate) mod sinks;
 pub mod sources;
-
-type BoxedErr = Box<dyn Error + Send>;
+pub(crate) mod token_manager;
 
 #[actix_web::main]
 async fn main() {
     // Defining consts
-    let USER_AGENT: String = std::env::var("USER_AGENT").expect("Missing USER_AGENT env variable.");
+    let user_agent: String = std::env::var("USER_AGENT").expect("Missing USER_AGENT env variable.");
 
     // Logging service start
     println!("Scraper service starting.");
 
     // Creating the errors channel
     // This channel will handle all errors that are generated during the runtime of this serivce.
-    let (errors_tx, errors_rx) = tokio::sync::mpsc::channel::<BoxedErr>(1024);
+    let (errors_tx, errors_rx) = tokio::sync::mpsc::channel::<ScraperError>(1024);
+
+    // Opening the outbox
+    // Results are persisted here until they have been published.
+    let outbox = Outbox::from_env();
+
+    // Opening the dead-letter store
+    // Results that exhaust their publish retries are moved here.
+    let dead_letters = DeadLetterStore::from_env();
 
     // Creating channels for postal svc
     // The postal service will be responsible for the processing of the outbound messages
-    let (postal_tx, postal_rx) = tokio::sync::mpsc::channel::<ScrapingResult>(1024);
+    let (postal_tx, postal_rx) = tokio::sync::mpsc::channel::<OutboxEntry>(1024);
+    let postal_sender = PostalSender::new(outbox.clone(), postal_tx);
+
+    // Snapshotting the results left in the outbox by a previous run
+    // This happens before any producer starts, so that it only holds results that are not queued yet.
+    let pending = outbox.pending();
 
     // Spawning the postal service
+    let postal_svc_dead_letters = dead_letters.clone();
     let postal_svc_errors_tx = errors_tx.clone();
-    tokio::task::spawn(async move { spawn_postal_service(postal_rx, postal_svc_errors_tx).await });
+    tokio::task::spawn(async move {
+        spawn_postal_service(
+            postal_rx,
+            outbox,
+            postal_svc_dead_letters,
+            postal_svc_errors_tx,
+        )
+        .await
+    });
+
+    // Replaying the results left in the outbox by a previous run
+    let replay_postal_sender = postal_sender.clone();
+    let replay_errors_tx = errors_tx.clone();
+    tokio::task::spawn(async move {
+        let replayed = match pending {
+            Ok(pending) => replay_postal_sender.replay_pending(pending).await,
+            Err(e) => Err(e),
+        };
+        match replayed {
+            Ok(pending_count) => println!(
+                "Replayed {} pending result(s) from the outbox.",
+                pending_count
+            ),
+            Err(e) => replay_errors_tx
+                .send(e)
+                .await
+                .expect("Unexpected error when sending to the error channel."),
+        }
+    });
 
     // Spawning the error handler service
+    let error_svc_errors_tx = errors_tx.clone();
     tokio::task::spawn(async move {
-        spawn_error_handler_service(errors_rx).await;
+        spawn_error_handler_service(errors_rx, error_svc_errors_tx).await;
     });
 
+    // Creating the dedup store
+    // Shared by the push endpoint and the pull consumer, so that a message is only processed once.
+    let dedup_store = web::Data::new(DedupStore::from_env());
+
+    // Creating the domain cool-downs
+    // Shared by the push endpoint and the pull consumer, so that a blocked domain is left alone by both.
+    let domain_cooldowns = web::Data::new(DomainCooldowns::from_env());
+
+    // Registering the sources enabled in this build
+    let source_registry = web::Data::new(SourceRegistry::with_enabled_sources());
+    println!(
+        "Enabled sources: {}",
+        source_registry.source_names().join(", ")
+    );
+
+    // Spawning the pull consumer, if a subscription is configured
+    // This runs alongside the push endpoint, which remains available.
+    if let Ok(subscription) = std::env::var("PULL_SUBSCRIPTION") {
+        let pull_client = ClientBuilder::new()
+            .user_agent(&user_agent)
+            .build()
+            .expect("Failed to build reqwest client.");
+        let pull_postal_sender = postal_sender.clone();
+        let pull_errors_tx = errors_tx.clone();
+        let pull_dedup_store = dedup_store.clone();
+        let pull_domain_cooldowns = domain_cooldowns.clone();
+        let pull_source_registry = source_registry.clone();
+        tokio::task::spawn(async move {
+            spawn_pull_consumer_service(
+                subscription,
+                pull_client,
+                pull_postal_sender,
+                pull_errors_tx,
+                pull_dedup_store,
+                pull_domain_cooldowns,
+                pull_source_registry,
+            )
+            .await
+        });
+    }
+
+    // Loading the push authenticator
+    // Push requests are only verified when PUSH_AUTH_AUDIENCE is set.
+    let push_authenticator = web::Data::new(PushAuthenticator::from_env());
+
+    // Loading the admin authenticator
+    // The admin endpoints are only served when ADMIN_TOKEN is set.
+    let admin_authenticator = AdminAuthenticator::from_env().map(web::Data::new);
+    if admin_authenticator.is_none() {
+        println!("ADMIN_TOKEN is not set, the admin endpoints are disabled.");
+    }
+
     // Starting up the HTTPServer
     HttpServer::new(move || {
         // Constructing reqwest client service
         let req_client = ClientBuilder::new()
-            .user_agent(&USER_AGENT)
+            .user_agent(&user_agent)
             .build()
             .expect("Failed to build reqwest client.");
 
         // Constructing the App instance
         App::new()
             .app_data(web::Data::new(req_client))
-            .app_data(web::Data::new(postal_tx.clone())) // Wrapped in a ARC
+            .app_data(web::Data::new(postal_sender.clone())) // Wrapped in a ARC
             .app_data(web::Data::new(errors_tx.clone())) // Wrapped in a ARC
+            .app_data(web::Data::new(dead_letters.clone())) // Wrapped in a ARC
+            .app_data(push_authenticator.clone())
+            .app_data(dedup_store.clone())
+            .app_data(domain_cooldowns.clone())
+            .app_data(source_registry.clone())
             .service(resource("/hello-world").route(route().guard(Get()).to(hello_world)))
             .service(
                 resource("/scraping-request")
                     .route(route().guard(Post()).to(scraping_request_handler)),
             )
+            .configure(|cfg| {
+                if let Some(admin_authenticator) = &admin_authenticator {
+                    cfg.app_data(admin_authenticator.clone())
+                        .service(
+                            resource("/admin/dead-letters")
+                                .route(route().guard(Get()).to(dead_letters_handler)),
+                        )
+                        .service(
+                            resource("/admin/dead-letters/redrive")
+                                .route(route().guard(Post()).to(redrive_dead_letters_handler)),
+                        );
+                }
+            })
     })
     .bind(("0.0.0.0", 8080))
     .expect("Failed to bind to requested port.")
@@ -0,0 +1,73 @@
+use prost::Message;
+
+use crate::{errors::ScraperError, scraping::results::ScrapingResult};
+
+/// A scraping result that has been persisted to the outbox and is waiting to be published.
+#[derive(Debug)]
+pub(crate) struct OutboxEntry {
+    pub(crate) id: u64,
+    pub(crate) result: ScrapingResult,
+}
+
+/// Local store of results that have not been published yet.
+/// Results are written here before they are handed to the postal service and are only removed
+/// once they have been published, so that they survive publish failures and restarts.
+#[derive(Clone)]
+pub(crate) struct Outbox {
+    db: sled::Db,
+}
+
+impl Outbox {
+    pub(crate) fn open(path: &str) -> sled::Result<Self> {
+        let db = sled::open(path)?;
+        Ok(Outbox { db })
+    }
+
+    pub(crate) fn from_env() -> Self {
+        let path = std::env::var("OUTBOX_PATH").unwrap_or_else(|_| "outbox".to_string());
+        Outbox::open(&path).expect("Failed to open the outbox.")
+    }
+
+    /// Persists the result and waits for it to be flushed to disk.
+    pub(crate) async fn insert(&self, result: ScrapingResult) -> Result<OutboxEntry, ScraperError> {
+        let id = self.db.generate_id().map_err(ScraperError::storage)?;
+
+        // Big-endian keys keep the entries ordered by insertion
+        self.db
+            .insert(id.to_be_bytes(), result.encode_to_vec())
+            .map_err(ScraperError::storage)?;
+        self.db.flush_async().await.map_err(ScraperError::storage)?;
+
+        Ok(OutboxEntry { id, result })
+    }
+
+    pub(crate) async fn remove(&self, ids: &[u64]) -> Result<(), ScraperError> {
+        let mut batch = sled::Batch::default();
+        for id in ids {
+            batch.remove(id.to_be_bytes().to_vec());
+        }
+
+        self.db.apply_batch(batch).map_err(ScraperError::storage)?;
+        self.db.flush_async().await.map_err(ScraperError::storage)?;
+
+        Ok(())
+    }
+
+    /// Returns every entry that is still waiting to be published, oldest first.
+    pub(crate) fn pending(&self) -> Result<Vec<OutboxEntry>, ScraperError> {
+        self.db
+            .iter()
+            .map(|item| {
+                let (key, value) = item.map_err(ScraperError::storage)?;
+                let id = u64::from_be_bytes(
+                    key.as_ref()
+                        .try_into()
+                        .expect("Outbox keys are always 8 bytes long."),
+                );
+                let result =
+                    ScrapingResult::decode(value.as_ref()).map_err(ScraperError::storage)?;
+                Ok(OutboxEntry { id, result })
+            })
+            .collect()
+    }
+}
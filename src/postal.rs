@@ -1,114 +1,264 @@
-use reqwest::Client;
-use tokio::sync::mpsc::{Receiver, Sender};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tokio::{
+    sync::mpsc::{Receiver, Sender},
+    time::Instant,
+};
 
 use crate::{
-    pubsub::{get_access_token, OutboundPubSubPayload},
-    scraping::results::ScrapingResult,
-    BoxedErr,
+    backoff::BackoffConfig,
+    config::env_or,
+    dead_letter::{DeadLetterRecord, DeadLetterStore},
+    errors::ScraperError,
+    outbox::{Outbox, OutboxEntry},
+    scraping::{json_results::ScrapingResultJson, results::ScrapingResult},
+    sinks::result_sink_from_env,
 };
 
+/// Hands results to the postal service, persisting them to the outbox first.
+#[derive(Clone)]
+pub(crate) struct PostalSender {
+    outbox: Outbox,
+    postal_tx: Sender<OutboxEntry>,
+}
+
+impl PostalSender {
+    pub(crate) fn new(outbox: Outbox, postal_tx: Sender<OutboxEntry>) -> Self {
+        PostalSender { outbox, postal_tx }
+    }
+
+    pub(crate) async fn send(&self, result: ScrapingResult) -> Result<(), ScraperError> {
+        let entry = self.persist(result).await?;
+        self.queue(entry).await
+    }
+
+    /// Persists the result to the outbox, from where it is replayed on restart until published.
+    pub(crate) async fn persist(
+        &self,
+        result: ScrapingResult,
+    ) -> Result<OutboxEntry, ScraperError> {
+        self.outbox.insert(result).await
+    }
+
+    /// Hands a persisted result to the postal service.
+    pub(crate) async fn queue(&self, entry: OutboxEntry) -> Result<(), ScraperError> {
+        self.postal_tx
+            .send(entry)
+            .await
+            .map_err(|_| ScraperError::internal("Postal service has stopped."))
+    }
+
+    /// Re-queues the entries left in the outbox by a previous run.
+    /// The snapshot must be taken before anything calls `send`, or new results are queued twice.
+    /// Returns the number of entries that were re-queued.
+    pub(crate) async fn replay_pending(
+        &self,
+        pending: Vec<OutboxEntry>,
+    ) -> Result<usize, ScraperError> {
+        let pending_count = pending.len();
+        for entry in pending {
+            self.postal_tx
+                .send(entry)
+                .await
+                .map_err(|_| ScraperError::internal("Postal service has stopped."))?;
+        }
+
+        Ok(pending_count)
+    }
+}
+
+/// Pub/Sub rejects publish requests containing more than 1000 messages.
+const MAX_MESSAGES_PER_PUBLISH: usize = 1000;
+
+/// Limits on how many results are grouped into a single publish request.
+pub(crate) struct BatchConfig {
+    max_messages: usize,
+    max_bytes: usize,
+    linger: Duration,
+}
+
+impl BatchConfig {
+    pub(crate) fn from_env() -> Self {
+        let max_messages: usize = env_or("PUBLISH_BATCH_MAX_MESSAGES", 100);
+        let max_bytes: usize = env_or("PUBLISH_BATCH_MAX_BYTES", 1_000_000);
+        let linger_ms: u64 = env_or("PUBLISH_BATCH_LINGER_MS", 100);
+
+        BatchConfig {
+            max_messages: max_messages.clamp(1, MAX_MESSAGES_PER_PUBLISH),
+            max_bytes,
+            linger: Duration::from_millis(linger_ms),
+        }
+    }
+}
+
+/// A batch of results along with the ids of their outbox entries.
+struct Batch {
+    ids: Vec<u64>,
+    results: Vec<ScrapingResultJson>,
+}
+
+/// Drains the postal channel into batches of results.
+/// The byte limit is measured against the JSON encoding of each result.
+struct Batcher {
+    postal_rx: Receiver<OutboxEntry>,
+    batch_config: BatchConfig,
+    carry_over: Option<(u64, ScrapingResultJson, usize)>, // Result that did not fit into the previous batch.
+}
+
+impl Batcher {
+    fn new(postal_rx: Receiver<OutboxEntry>, batch_config: BatchConfig) -> Self {
+        Batcher {
+            postal_rx,
+            batch_config,
+            carry_over: None,
+        }
+    }
+
+    /// Waits for the next result, then keeps collecting until the batch is full or the linger time
+    /// has elapsed. Returns `None` once the channel is closed and fully drained.
+    async fn next_batch(&mut self) -> Option<Batch> {
+        let (first_id, first_result, mut batch_bytes) = match self.carry_over.take() {
+            Some(carry_over) => carry_over,
+            None => measure_entry(self.postal_rx.recv().await?),
+        };
+
+        let deadline = Instant::now() + self.batch_config.linger;
+        let mut batch = Batch {
+            ids: vec![first_id],
+            results: vec![first_result],
+        };
+
+        while batch.results.len() < self.batch_config.max_messages {
+            let entry = match tokio::time::timeout_at(deadline, self.postal_rx.recv()).await {
+                Ok(Some(entry)) => entry,
+                Ok(None) | Err(_) => break, // Channel closed or linger time elapsed.
+            };
+
+            let (id, result, result_bytes) = measure_entry(entry);
+            if batch_bytes + result_bytes > self.batch_config.max_bytes {
+                self.carry_over = Some((id, result, result_bytes));
+                break;
+            }
+            batch_bytes += result_bytes;
+            batch.ids.push(id);
+            batch.results.push(result);
+        }
+
+        Some(batch)
+    }
+}
+
+fn measure_entry(entry: OutboxEntry) -> (u64, ScrapingResultJson, usize) {
+    let result = ScrapingResultJson::from(entry.result);
+    let result_bytes = serde_json::to_vec(&result)
+        .expect("Unexpected error when serializing the ScrapingResult into a JSON string.")
+        .len();
+    (entry.id, result, result_bytes)
+}
+
+/// Results are only removed from the outbox once they have been published or dead-lettered.
+async fn dead_letter_batch(
+    dead_letters: &DeadLetterStore,
+    results: Vec<ScrapingResultJson>,
+    failure_reason: String,
+    attempts: u32,
+) -> Result<(), ScraperError> {
+    let dead_lettered_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Unable to retrieve system time.")
+        .as_secs();
+
+    let records = results
+        .into_iter()
+        .map(|result| DeadLetterRecord {
+            result,
+            failure_reason: failure_reason.clone(),
+            attempts,
+            dead_lettered_at,
+        })
+        .collect::<Vec<_>>();
+
+    println!("Dead-lettering {} result(s).", records.len());
+    dead_letters.append(&records).await
+}
+
 pub(crate) async fn spawn_postal_service(
-    mut postal_rx: Receiver<ScrapingResult>,
-    errors_tx: Sender<BoxedErr>,
+    postal_rx: Receiver<OutboxEntry>,
+    outbox: Outbox,
+    dead_letters: DeadLetterStore,
+    errors_tx: Sender<ScraperError>,
 ) {
-    // CONSTANTS
-    let PUBLISH_TOPIC_ENDPOINT: String =
-        std::env::var("PUBLISH_TOPIC").expect("Missing PUBLISH_TOPIC env variable.");
-
     // Logging
     println!("Starting up postal service.");
 
     // Creating a local reqwest client
     let client = reqwest::Client::new();
 
+    // Creating the configured result sink
+    let result_sink = result_sink_from_env(client, errors_tx.clone());
+    println!(
+        "Publishing results to the {} sink.",
+        result_sink.get_sink_name()
+    );
+
+    // Creating the retry policy
+    let backoff_config = BackoffConfig::from_env();
+
+    // Creating the batcher
+    let mut batcher = Batcher::new(postal_rx, BatchConfig::from_env());
+
     // Creating read loop
-    while let Some(scraping_result) = postal_rx.recv().await {
-        // Serializing the payload
-        match OutboundPubSubPayload::from(scraping_result).serialize_payload() {
-            Ok(serialized_payload) => {
-                // Publishing the payload
-                let mut count = 0;
-                loop {
-                    count += 1; // Incrementing the count
-                    match publish_payload(
-                        &client,
-                        &PUBLISH_TOPIC_ENDPOINT,
-                        serialized_payload.clone(),
-                    )
-                    .await
-                    {
-                        Ok(response) if response.status().is_success() => {
-                            match deserialize_response(response).await {
-                                Ok(deserialized_response) => {
-                                    // Simply logging the response for now
-                                    println!("Successfully published payload. See response below:");
-                                    println!("{:#?}", deserialized_response);
-                                }
-                                Err(e) => {
-                                    // Sending the error to the error channel
-                                    errors_tx.send(Box::new(e)).await.expect(
-                                        "Unexpected error when sending to the error channel.",
-                                    );
-                                }
-                            }
-                            break;
-                        }
-                        unsuccessful => {
-                            // Getting the error
-                            let err = match unsuccessful {
-                                Ok(response) => {
-                                    response.error_for_status().unwrap_err()
-                                    // Should always return an error.
-                                }
-                                Err(e) => e,
-                            };
-
-                            // Sending the error to the error channel
-                            errors_tx
-                                .send(Box::new(err))
-                                .await
-                                .expect("Unexpected error when sending to the error channel.");
-                            if count >= 5 {
-                                break;
-                            }
-                        }
+    while let Some(batch) = batcher.next_batch().await {
+        // Publishing the batch
+        let mut count = 0;
+        let published = loop {
+            count += 1; // Incrementing the count
+            match result_sink.publish(&batch.results).await {
+                Ok(()) => {
+                    println!("Successfully published {} result(s).", batch.results.len());
+                    break Ok(());
+                }
+                Err(e) => {
+                    let failure_reason = e.to_string();
+                    let retryable = e.is_retryable();
+                    let delay = backoff_config.delay(count, e.retry_after());
+
+                    // Sending the error to the error channel
+                    errors_tx
+                        .send(e.into())
+                        .await
+                        .expect("Unexpected error when sending to the error channel.");
+                    if !retryable || count >= backoff_config.max_attempts {
+                        break Err(failure_reason);
                     }
+
+                    // Backing off before the next attempt
+                    tokio::time::sleep(delay).await;
                 }
             }
-            Err(e) => {
-                errors_tx
-                    .send(Box::new(e))
-                    .await
-                    .expect("Unexpected error when sending to the error channel.");
+        };
+
+        // Moving the results that could not be published to the dead-letter store
+        let settled = match published {
+            Ok(()) => Ok(()),
+            Err(failure_reason) => {
+                dead_letter_batch(&dead_letters, batch.results, failure_reason, count).await
             }
+        };
+
+        // Removing the settled results from the outbox
+        // If dead-lettering failed, the results stay in the outbox and are replayed on restart.
+        let removed = match settled {
+            Ok(()) => outbox.remove(&batch.ids).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = removed {
+            errors_tx
+                .send(e)
+                .await
+                .expect("Unexpected error when sending to the error channel.");
         }
     }
 
     unreachable!("Postal service has stopped.")
 }
-
-async fn publish_payload(
-    client: &Client,
-    topic: &str,
-    serialized_payload: String,
-) -> Result<reqwest::Response, reqwest::Error> {
-    // Getting the access token
-    let auth_details = get_access_token(client)
-        .await
-        .expect("Failed to get access token.");
-
-    client
-        .post(topic)
-        .header(
-            "Authorization",
-            format!("Bearer {}", auth_details.access_token),
-        )
-        .body(serialized_payload)
-        .send()
-        .await?
-        .error_for_status()
-}
-
-async fn deserialize_response(response: reqwest::Response) -> Result<String, reqwest::Error> {
-    response.text().await
-}
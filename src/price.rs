@@ -0,0 +1,393 @@
+use std::fmt::Display;
+
+use crate::scraping::results;
+
+/// An exact amount of money, held in the minor units of its currency (e.g. cents).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Money {
+    pub amount_minor: i64,
+    pub currency: String, // ISO 4217 currency code
+}
+
+/// Currencies whose minor unit is not 1/100 of the major unit.
+const MINOR_UNIT_EXCEPTIONS: &[(&str, u32)] = &[
+    ("BHD", 3),
+    ("CLP", 0),
+    ("IQD", 3),
+    ("ISK", 0),
+    ("JOD", 3),
+    ("JPY", 0),
+    ("KRW", 0),
+    ("KWD", 3),
+    ("LYD", 3),
+    ("OMR", 3),
+    ("TND", 3),
+    ("VND", 0),
+];
+
+/// Most decimals an amount given with a fixed number of them can have. An `i64` holds 18 full digits.
+const MAX_SCALED_DECIMALS: u32 = 18;
+
+/// Unambiguous currency symbols, used when a price does not come with a currency code.
+const CURRENCY_SYMBOLS: &[(&str, &str)] = &[
+    ("S$", "SGD"),
+    ("A$", "AUD"),
+    ("C$", "CAD"),
+    ("R$", "BRL"),
+    ("€", "EUR"),
+    ("£", "GBP"),
+    ("¥", "JPY"),
+    ("￥", "JPY"),
+    ("₹", "INR"),
+    ("₩", "KRW"),
+    ("zł", "PLN"),
+];
+
+impl Money {
+    pub fn new(amount_minor: i64, currency: impl Into<String>) -> Self {
+        Money {
+            amount_minor,
+            currency: currency.into().to_uppercase(),
+        }
+    }
+
+    /// Converts an amount given with a fixed number of decimals into the currency's minor units,
+    /// e.g. the cents that Shopify uses whatever the currency.
+    /// Returns `None` if there are too many decimals, or if the amount does not fit once rescaled.
+    pub fn from_scaled(amount: i64, decimals: u32, currency: &str) -> Option<Money> {
+        if decimals > MAX_SCALED_DECIMALS {
+            return None;
+        }
+
+        let minor_units = Money::minor_units(currency);
+        let amount_minor = match minor_units.cmp(&decimals) {
+            std::cmp::Ordering::Less => amount / 10_i64.checked_pow(decimals - minor_units)?,
+            _ => amount.checked_mul(10_i64.checked_pow(minor_units - decimals)?)?,
+        };
+        Some(Money::new(amount_minor, currency))
+    }
+
+    /// Number of decimal places used by the currency, as defined by ISO 4217.
+    pub fn minor_units(currency: &str) -> u32 {
+        MINOR_UNIT_EXCEPTIONS
+            .iter()
+            .find(|(code, _)| code.eq_ignore_ascii_case(currency))
+            .map_or(2, |(_, units)| *units)
+    }
+
+    /// Guesses the currency from a symbol or ISO code within a displayed price, e.g. "1.299,00 €".
+    pub fn detect_currency(raw: &str) -> Option<String> {
+        let symbol = CURRENCY_SYMBOLS
+            .iter()
+            .find(|(symbol, _)| raw.contains(symbol))
+            .map(|(_, currency)| currency.to_string());
+
+        symbol.or_else(|| {
+            raw.split(|c: char| !c.is_ascii_alphabetic())
+                .find(|word| word.len() == 3 && word.chars().all(|c| c.is_ascii_uppercase()))
+                .map(str::to_string)
+        })
+    }
+
+    /// Parses a displayed price such as "S$1,299.00", "1.299,00 €", "1 299,99 kr" or "￥12,800".
+    ///
+    /// Currency symbols, codes and whitespace are ignored. When no decimal separator is given, the
+    /// last separator is taken as the decimal separator, unless it is followed by exactly three
+    /// digits in a currency that does not use three decimal places. When the given separator does
+    /// not appear, any other separator must group thousands, so "12.99" is rejected for `','`. Decimals beyond those of the
+    /// currency are only accepted if they are zeros, e.g. "12800.0" JPY. Returns `None` for anything
+    /// that is not a single price, e.g. ranges such as "$10 - $20" or "$10 to $20".
+    pub fn parse(raw: &str, currency: &str, decimal_separator: Option<char>) -> Option<Money> {
+        let minor_units = Money::minor_units(currency);
+
+        // Keeping only the digits and separators
+        let chars = raw.chars().collect::<Vec<_>>();
+        let mut cleaned = String::new();
+        let mut gap = String::new(); // Characters skipped since the last digit
+        for (i, c) in chars.iter().copied().enumerate() {
+            match c {
+                '0'..='9' => {
+                    // Runs of digits may only be split by a single space grouping thousands, as in
+                    // "1 299,99". Anything else separates two prices.
+                    if cleaned.contains(|c: char| c.is_ascii_digit()) && !gap.is_empty() {
+                        let group_len =
+                            chars[i..].iter().take_while(|c| c.is_ascii_digit()).count();
+                        let is_space =
+                            gap.chars().count() == 1 && gap.chars().all(char::is_whitespace);
+                        if !is_space || group_len != 3 {
+                            return None;
+                        }
+                    }
+                    cleaned.push(c);
+                    gap.clear();
+                }
+                '.' | ',' | '\'' => {
+                    cleaned.push(c);
+                    gap.clear();
+                }
+                // Currency symbols and codes
+                '$' | '"' => gap.push(c),
+                c if c.is_whitespace() || !c.is_ascii() || c.is_ascii_alphabetic() => gap.push(c),
+                _ => return None,
+            }
+        }
+        let cleaned = cleaned.trim_matches(|c: char| !c.is_ascii_digit());
+        if cleaned.is_empty() {
+            return None;
+        }
+
+        // Working out which separator, if any, marks the decimals
+        let decimal_separator = match decimal_separator {
+            Some(separator) if cleaned.contains(separator) => Some(separator),
+            Some(_) => {
+                // Without decimals, e.g. "1,299" for `'.'`, but not "1,5", which uses another format
+                let groups_thousands = cleaned
+                    .split(['.', ',', '\''])
+                    .skip(1)
+                    .all(|group| group.len() == 3);
+                if !groups_thousands {
+                    return None;
+                }
+                None
+            }
+            None => guess_decimal_separator(cleaned, minor_units),
+        };
+
+        // Splitting into the whole and fractional parts
+        let (whole, fraction) = match decimal_separator {
+            Some(separator) => {
+                let (whole, fraction) = cleaned.rsplit_once(separator)?;
+                if whole.contains(separator) {
+                    return None;
+                }
+                (whole, fraction)
+            }
+            None => (cleaned, ""),
+        };
+        let whole = whole
+            .chars()
+            .filter(|c| c.is_ascii_digit())
+            .collect::<String>();
+        if !fraction.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        // Dropping trailing zeros beyond the currency's decimals, e.g. the ".0" of "12800.0" JPY
+        let fraction = match fraction.split_at_checked(minor_units as usize) {
+            Some((fraction, extra)) if extra.chars().all(|c| c == '0') => fraction,
+            Some(_) => return None,
+            None => fraction,
+        };
+
+        // Combining both parts into minor units
+        let scale = 10_i64.pow(minor_units);
+        let whole = whole.parse::<i64>().ok()?;
+        let fraction = format!("{:0<width$}", fraction, width = minor_units as usize);
+        let fraction = match fraction.is_empty() {
+            true => 0,
+            false => fraction.parse::<i64>().ok()?,
+        };
+        let amount_minor = whole.checked_mul(scale)?.checked_add(fraction)?;
+
+        Some(Money::new(amount_minor, currency))
+    }
+
+    /// Percentage saved on the list price, if this price is lower.
+    pub fn savings_percentage(&self, list_price: &Money) -> Option<u32> {
+        if list_price.currency != self.currency || self.amount_minor >= list_price.amount_minor {
+            return None;
+        }
+
+        let saved = list_price.amount_minor - self.amount_minor;
+        Some((saved as f64 * 100.0 / list_price.amount_minor as f64).round() as u32)
+    }
+
+    /// Approximate value in major units, for consumers of the legacy `price` field.
+    pub fn as_f32(&self) -> f32 {
+        let scale = 10_i64.pow(Money::minor_units(&self.currency));
+        (self.amount_minor as f64 / scale as f64) as f32
+    }
+}
+
+fn guess_decimal_separator(cleaned: &str, minor_units: u32) -> Option<char> {
+    let position = cleaned.rfind(['.', ','])?;
+    let separator = cleaned[position..].chars().next()?;
+    let digits_after = cleaned.len() - position - 1;
+
+    // Both kinds of separator present: the last one marks the decimals
+    let other = if separator == '.' { ',' } else { '.' };
+    if cleaned.contains(other) {
+        return Some(separator);
+    }
+
+    // Repeated separators only group thousands, e.g. "1,299,000"
+    if cleaned.matches(separator).count() > 1 {
+        return None;
+    }
+
+    match digits_after {
+        3 if minor_units != 3 => None,
+        _ => Some(separator),
+    }
+}
+
+impl Display for Money {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let minor_units = Money::minor_units(&self.currency) as usize;
+        let scale = 10_i64.pow(minor_units as u32);
+        let sign = if self.amount_minor < 0 { "-" } else { "" };
+        let amount = self.amount_minor.unsigned_abs();
+        let (whole, fraction) = (amount / scale as u64, amount % scale as u64);
+        match minor_units {
+            0 => write!(f, "{}{} {}", sign, whole, self.currency),
+            _ => write!(
+                f,
+                "{}{}.{:0width$} {}",
+                sign,
+                whole,
+                fraction,
+                self.currency,
+                width = minor_units
+            ),
+        }
+    }
+}
+
+impl From<Money> for results::Money {
+    fn from(value: Money) -> Self {
+        results::Money {
+            amount_minor: value.amount_minor,
+            currency: value.currency,
+        }
+    }
+}
+
+impl From<results::Money> for Money {
+    fn from(value: results::Money) -> Self {
+        Money::new(value.amount_minor, value.currency)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Money;
+
+    #[test]
+    fn parses_displayed_prices() {
+        let cases: &[(&str, &str, Option<char>, Option<i64>)] = &[
+            // Separators guessed from the price
+            ("1.299,00 €", "EUR", None, Some(129_900)),
+            ("S$1,299.00", "SGD", None, Some(129_900)),
+            ("1 299,99 kr", "SEK", None, Some(129_999)),
+            ("1\u{a0}299,99 kr", "SEK", None, Some(129_999)),
+            ("￥12,800", "JPY", None, Some(12_800)),
+            ("$1,299", "USD", None, Some(129_900)),
+            ("1,299,000", "USD", None, Some(129_900_000)),
+            ("12.5", "USD", None, Some(1_250)),
+            ("CHF 1'299.50", "CHF", None, Some(129_950)),
+            // Separators given by the source
+            ("12.99", "USD", Some('.'), Some(1_299)),
+            ("1,299.00", "USD", Some('.'), Some(129_900)),
+            ("1.299,00", "EUR", Some(','), Some(129_900)),
+            ("12", "USD", Some('.'), Some(1_200)),
+            ("1,299", "USD", Some('.'), Some(129_900)),
+            ("1.299", "EUR", Some(','), Some(129_900)),
+            ("1,299,000", "USD", Some('.'), Some(129_900_000)),
+            // Separators other than the given one
+            ("12.99", "EUR", Some(','), None),
+            ("1,5", "USD", Some('.'), None),
+            ("1,50", "USD", Some('.'), None),
+            ("1,2345", "USD", Some('.'), None),
+            // Zero-decimal currencies
+            ("12800.0", "JPY", Some('.'), Some(12_800)),
+            ("12800.00", "JPY", None, Some(12_800)),
+            ("12800", "KRW", Some('.'), Some(12_800)),
+            ("12800.5", "JPY", Some('.'), None),
+            // Three-decimal currencies
+            ("12.345", "KWD", None, Some(12_345)),
+            ("1,234.500", "BHD", None, Some(1_234_500)),
+            ("12.5", "KWD", Some('.'), Some(12_500)),
+            // Extra decimals are only accepted when they are zeros
+            ("19.990", "USD", Some('.'), Some(1_999)),
+            ("19.999", "USD", Some('.'), None),
+            // Not a single price
+            ("$10 - $20", "USD", Some('.'), None),
+            ("$10 to $20", "USD", Some('.'), None),
+            ("10 20", "USD", None, None),
+            ("12.99€ 15.99€", "EUR", Some('.'), None),
+            ("", "USD", None, None),
+            ("Currently unavailable", "USD", None, None),
+        ];
+
+        for (raw, currency, decimal_separator, expected) in cases {
+            let parsed = Money::parse(raw, currency, *decimal_separator);
+            assert_eq!(
+                parsed.map(|money| money.amount_minor),
+                *expected,
+                "parsing {:?} as {}",
+                raw,
+                currency
+            );
+        }
+    }
+
+    #[test]
+    fn detects_currencies() {
+        assert_eq!(Money::detect_currency("S$1,299.00").as_deref(), Some("SGD"));
+        assert_eq!(Money::detect_currency("1.299,00 €").as_deref(), Some("EUR"));
+        assert_eq!(Money::detect_currency("USD 12.99").as_deref(), Some("USD"));
+        assert_eq!(Money::detect_currency("12.99"), None);
+    }
+
+    #[test]
+    fn rescales_fixed_decimal_amounts() {
+        assert_eq!(
+            Money::from_scaled(1_999, 2, "USD"),
+            Some(Money::new(1_999, "USD"))
+        );
+        assert_eq!(
+            Money::from_scaled(128_000, 2, "JPY"),
+            Some(Money::new(1_280, "JPY"))
+        );
+        assert_eq!(
+            Money::from_scaled(1_999, 2, "KWD"),
+            Some(Money::new(19_990, "KWD"))
+        );
+        assert_eq!(
+            Money::from_scaled(1_999_000_000_000_000_000, 18, "JPY"),
+            Some(Money::new(1, "JPY"))
+        );
+
+        // Overflowing amounts and scales
+        assert_eq!(Money::from_scaled(1_999, 19, "USD"), None);
+        assert_eq!(Money::from_scaled(1_999, u32::MAX, "USD"), None);
+        assert_eq!(Money::from_scaled(i64::MAX / 2, 2, "KWD"), None);
+    }
+
+    #[test]
+    fn computes_savings() {
+        let list_price = Money::new(2_000, "USD");
+        assert_eq!(
+            Money::new(1_500, "USD").savings_percentage(&list_price),
+            Some(25)
+        );
+        assert_eq!(
+            Money::new(2_500, "USD").savings_percentage(&list_price),
+            None
+        );
+        assert_eq!(
+            Money::new(1_500, "EUR").savings_percentage(&list_price),
+            None
+        );
+    }
+
+    #[test]
+    fn displays_amounts_in_major_units() {
+        assert_eq!(Money::new(129_900, "USD").to_string(), "1299.00 USD");
+        assert_eq!(Money::new(5, "USD").to_string(), "0.05 USD");
+        assert_eq!(Money::new(-50, "USD").to_string(), "-0.50 USD");
+        assert_eq!(Money::new(-1_250, "USD").to_string(), "-12.50 USD");
+        assert_eq!(Money::new(12_800, "JPY").to_string(), "12800 JPY");
+        assert_eq!(Money::new(12_345, "KWD").to_string(), "12.345 KWD");
+    }
+}
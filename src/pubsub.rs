@@ -1,18 +1,20 @@
-use std::{collections::HashMap, error::Error};
+use std::collections::HashMap;
 
 use base64::{DecodeError, Engine};
 use prost::EncodeError;
-use reqwest::Client;
+use reqwest::{Client, RequestBuilder};
+use tokio::sync::mpsc::Sender;
 
 use crate::{
+    config::env_or,
+    errors::ScraperError,
     scraping::{
         json_results::ScrapingResultJson, requests::ScrapingRequests, results::ScrapingResult,
     },
-    scraping_traits::Scraper,
+    sources::{DispatchedRequests, SourceRegistry},
+    token_manager::{spawn_token_refresh_service, TokenManager},
 };
 
-type BoxedErr = Box<dyn Error + Send>;
-
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 pub(crate) struct PubSubMessage {
     pub(crate) message: PubSubMessageMessage,
@@ -25,26 +27,95 @@ pub(crate) struct AuthDetails {
     pub(crate) expires_in: i32,
     pub(crate) token_type: String,
 }
-pub(crate) async fn get_access_token(client: &Client) -> Result<AuthDetails, BoxedErr> {
-    const URL_ENDPOINT: &str = "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
+/// Builds the metadata server token endpoint. The host can be overridden with `GCE_METADATA_HOST`.
+pub(crate) fn metadata_token_url() -> String {
+    let metadata_host = std::env::var("GCE_METADATA_HOST")
+        .unwrap_or_else(|_| "metadata.google.internal".to_string());
+    format!(
+        "http://{}/computeMetadata/v1/instance/service-accounts/default/token",
+        metadata_host
+    )
+}
 
+pub(crate) async fn get_access_token(
+    client: &Client,
+    metadata_token_url: &str,
+) -> Result<AuthDetails, ScraperError> {
     // Constructing the request call to the metadata server
     let raw_response: String = client
-        .get(URL_ENDPOINT)
+        .get(metadata_token_url)
         .header("Metadata-Flavor", "Google")
         .send()
         .await
-        .map_err(|e| Box::new(e) as BoxedErr)?
+        .and_then(|response| response.error_for_status())
+        .map_err(ScraperError::auth)?
         .text()
         .await
-        .map_err(|e| Box::new(e) as BoxedErr)?;
+        .map_err(ScraperError::auth)?;
 
-    let response: AuthDetails =
-        serde_json::from_str(&raw_response).map_err(|e| Box::new(e) as BoxedErr)?;
+    let response: AuthDetails = serde_json::from_str(&raw_response).map_err(ScraperError::auth)?;
 
     Ok(response)
 }
 
+/// Where Pub/Sub REST calls are sent and how they are authorised.
+/// Requests to the emulator or a custom endpoint with auth disabled are sent without a token.
+#[derive(Clone)]
+pub(crate) struct PubSubEndpoint {
+    base_url: String,
+    token_manager: Option<TokenManager>,
+}
+
+impl PubSubEndpoint {
+    /// `PUBSUB_EMULATOR_HOST` takes precedence over `PUBSUB_API_ENDPOINT`, and always disables auth.
+    /// Auth can also be disabled for a custom endpoint with `PUBSUB_SKIP_AUTH=true`.
+    pub(crate) fn from_env(client: Client, errors_tx: Sender<ScraperError>) -> Self {
+        let emulator_host = std::env::var("PUBSUB_EMULATOR_HOST").ok();
+        let skip_auth = emulator_host.is_some() || env_or("PUBSUB_SKIP_AUTH", false);
+        let base_url = match emulator_host {
+            Some(emulator_host) => format!("http://{}", emulator_host),
+            None => std::env::var("PUBSUB_API_ENDPOINT")
+                .unwrap_or_else(|_| "https://pubsub.googleapis.com".to_string()),
+        };
+
+        let token_manager = if skip_auth {
+            None
+        } else {
+            // Keeping the access token fresh in the background
+            let token_manager = TokenManager::new(client, metadata_token_url());
+            let refresh_svc_token_manager = token_manager.clone();
+            tokio::task::spawn(async move {
+                spawn_token_refresh_service(refresh_svc_token_manager, errors_tx).await
+            });
+            Some(token_manager)
+        };
+
+        PubSubEndpoint {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token_manager,
+        }
+    }
+
+    /// Builds the REST url for a resource path, e.g. `projects/p/topics/t:publish`.
+    pub(crate) fn url(&self, resource_path: &str) -> String {
+        format!("{}/v1/{}", self.base_url, resource_path)
+    }
+
+    /// Attaches a bearer token to the request, unless auth is disabled.
+    pub(crate) async fn authorize(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<RequestBuilder, ScraperError> {
+        match &self.token_manager {
+            Some(token_manager) => {
+                let access_token = token_manager.get_access_token().await?;
+                Ok(request.header("Authorization", format!("Bearer {}", access_token)))
+            }
+            None => Ok(request),
+        }
+    }
+}
+
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 #[allow(non_snake_case)]
 pub(crate) struct PubSubMessageMessage {
@@ -56,6 +127,13 @@ pub(crate) struct PubSubMessageMessage {
     pub(crate) publish_time: Option<String>,
 }
 
+impl PubSubMessageMessage {
+    /// Push deliveries carry both spellings of the message id.
+    pub(crate) fn get_message_id(&self) -> Option<&str> {
+        self.messageId.as_deref().or(self.message_id.as_deref())
+    }
+}
+
 /// This struct will be the request payload when sent to the PubSub service.
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 pub(crate) struct OutboundPubSubPayload {
@@ -73,6 +151,14 @@ impl FromIterator<ScrapingResult> for OutboundPubSubPayload {
     }
 }
 
+impl FromIterator<PubSubMessageMessage> for OutboundPubSubPayload {
+    fn from_iter<T: IntoIterator<Item = PubSubMessageMessage>>(iter: T) -> Self {
+        let messages = iter.into_iter().collect::<Vec<_>>();
+
+        OutboundPubSubPayload { messages }
+    }
+}
+
 impl OutboundPubSubPayload {
     pub fn serialize_payload(&self) -> Result<String, serde_json::Error> {
         serde_json::to_string(self)
@@ -85,43 +171,41 @@ impl PubSubMessage {
         base64::engine::general_purpose::STANDARD.decode(raw_payload) // Performing base64 decoding to decode the raw text string into a bytes sequence.
     }
 
-    fn decode_scraping_requests(self) -> Result<ScrapingRequests, Box<dyn Error + Send>> {
-        let payload_bytes = self.decode_base64().map_err(|e| Box::new(e) as BoxedErr)?;
+    fn decode_scraping_requests(self) -> Result<ScrapingRequests, ScraperError> {
+        let payload_bytes = self.decode_base64().map_err(ScraperError::decode)?;
 
         let scraping_requests: ScrapingRequests =
-            prost::Message::decode(&*payload_bytes).map_err(|e| Box::new(e) as BoxedErr)?; // Decoding the bytes sequence into the correct object.
+            prost::Message::decode(&*payload_bytes).map_err(ScraperError::decode)?; // Decoding the bytes sequence into the correct object.
 
         Ok(scraping_requests)
     }
 
-    pub(crate) fn get_scraping_requests(self) -> Result<Vec<Box<dyn Scraper + Send>>, BoxedErr> {
+    pub(crate) fn get_scraping_requests(
+        self,
+        registry: &SourceRegistry,
+    ) -> Result<DispatchedRequests, ScraperError> {
         let scraping_requests_wrapper = self.decode_scraping_requests()?;
-        let result = scraping_requests_wrapper
-            .requests
-            .into_iter()
-            .filter(|req| req.source.is_some())
-            .map(|req| {
-                let source = req.source.expect("Checked by filter step.");
-                let scraper: Box<dyn Scraper + Send> = match source {
-                    crate::scraping::requests::scraping_request::Source::Test(test) => {
-                        Box::new(test)
-                    }
-                    crate::scraping::requests::scraping_request::Source::Amzn(amzn) => {
-                        Box::new(amzn)
-                    }
-                };
-                scraper
-            })
-            .collect::<Vec<_>>();
-        Ok(result)
+        Ok(registry.dispatch(scraping_requests_wrapper.requests))
     }
 }
 
 impl ScrapingResult {
-    fn encode_to_pubsub(self) -> Result<PubSubMessageMessage, EncodeError> {
-        let json_result = ScrapingResultJson::from(self);
-        let serialized_payload = serde_json::to_string(&json_result)
-            .expect("Unexpected error when serializing the ScrapingResult into a JSON string.");
+    pub(crate) fn encode_to_pubsub(self) -> Result<PubSubMessageMessage, EncodeError> {
+        Ok(ScrapingResultJson::from(self).encode_to_pubsub())
+    }
+}
+
+impl ScrapingResultJson {
+    pub(crate) fn encode_to_pubsub(&self) -> PubSubMessageMessage {
+        PubSubMessageMessage::from_json(self)
+            .expect("Unexpected error when serializing the ScrapingResult into a JSON string.")
+    }
+}
+
+impl PubSubMessageMessage {
+    /// Wraps any JSON-serializable record as the base64 encoded data of an outbound message.
+    pub(crate) fn from_json<T: serde::Serialize>(record: &T) -> Result<Self, serde_json::Error> {
+        let serialized_payload = serde_json::to_string(record)?;
         let base64_encoded = base64::engine::general_purpose::STANDARD.encode(serialized_payload);
         Ok(PubSubMessageMessage {
             data: base64_encoded,
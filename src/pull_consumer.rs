@@ -0,0 +1,252 @@
+use std::{sync::Arc, time::Duration};
+
+use actix_web::web::Data;
+use reqwest::Client;
+use tokio::sync::{mpsc::Sender, Semaphore};
+
+use crate::{
+    config::env_or,
+    cooldown::DomainCooldowns,
+    dedup::DedupStore,
+    errors::{ErrorContext, ScraperError},
+    postal::PostalSender,
+    pubsub::{PubSubEndpoint, PubSubMessage, PubSubMessageMessage},
+    services::scraping_request,
+    sources::SourceRegistry,
+};
+
+/// Delay before pulling again after a failed pull.
+const PULL_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PullResponse {
+    #[serde(default)]
+    received_messages: Vec<ReceivedMessage>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReceivedMessage {
+    ack_id: String,
+    message: PubSubMessageMessage,
+}
+
+/// Consumes scraping requests from a pull subscription as an alternative to push delivery.
+/// Messages are only acked after their scraping results have been handed to the postal service,
+/// so the subscription's ack deadline should cover the time taken to scrape a message.
+#[derive(Clone)]
+struct PullConsumer {
+    client: Client,
+    endpoint: PubSubEndpoint,
+    subscription: String,
+}
+
+impl PullConsumer {
+    async fn pull(&self, max_messages: usize) -> Result<Vec<ReceivedMessage>, ScraperError> {
+        let url = self.endpoint.url(&format!("{}:pull", self.subscription));
+        let request = self
+            .client
+            .post(&url)
+            .json(&serde_json::json!({ "maxMessages": max_messages }));
+
+        let response: PullResponse = self
+            .endpoint
+            .authorize(request)
+            .await?
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| ScraperError::from_request(ErrorContext::url(&url), e))?
+            .json()
+            .await
+            .map_err(|e| ScraperError::from_request(ErrorContext::url(&url), e))?;
+
+        Ok(response.received_messages)
+    }
+
+    async fn acknowledge(&self, ack_id: String) -> Result<(), ScraperError> {
+        let url = self
+            .endpoint
+            .url(&format!("{}:acknowledge", self.subscription));
+        let request = self
+            .client
+            .post(&url)
+            .json(&serde_json::json!({ "ackIds": [ack_id] }));
+
+        self.endpoint
+            .authorize(request)
+            .await?
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| ScraperError::from_request(ErrorContext::url(&url), e))?;
+
+        Ok(())
+    }
+
+    /// Scrapes every request in the message, then acks it.
+    /// Messages that cannot be decoded are acked straight away, as redelivering them would not help.
+    #[allow(clippy::too_many_arguments)] // Mirrors the dependencies of the push endpoint.
+    async fn process(
+        &self,
+        received_message: ReceivedMessage,
+        scraping_client: Data<Client>,
+        result_channel: Data<PostalSender>,
+        errors_channel: Data<Sender<ScraperError>>,
+        dedup_store: Data<DedupStore>,
+        domain_cooldowns: Data<DomainCooldowns>,
+        source_registry: Data<SourceRegistry>,
+    ) -> Result<(), ScraperError> {
+        let message_id = received_message
+            .message
+            .get_message_id()
+            .map(str::to_string);
+        let payload = PubSubMessage {
+            message: received_message.message,
+            subscription: self.subscription.clone(),
+        };
+
+        match payload.get_scraping_requests(&source_registry) {
+            // Acking redelivered messages without scraping them again
+            Ok(_)
+                if message_id
+                    .as_ref()
+                    .is_some_and(|id| !dedup_store.insert_message_id(id)) => {}
+            Ok(dispatched) => {
+                // Reporting the requests for unknown sources
+                for e in dispatched.rejected {
+                    errors_channel
+                        .send(e)
+                        .await
+                        .expect("Unexpected error when sending to the error channel.");
+                }
+
+                let scraping_requests = dedup_store.deduplicate_requests(dispatched.scrapers);
+                let request_keys = dedup_store.request_keys(&scraping_requests);
+                let handed_off = scraping_request(
+                    scraping_requests,
+                    message_id.clone(),
+                    scraping_client,
+                    result_channel,
+                    errors_channel,
+                    domain_cooldowns,
+                )
+                .await;
+
+                // Leaving the message to be redelivered if some results were not handed off
+                // Its keys are forgotten, so that the redelivered copy is scraped again.
+                if !handed_off {
+                    if let Some(message_id) = &message_id {
+                        dedup_store.forget_message_id(message_id);
+                    }
+                    for request_key in request_keys {
+                        dedup_store.forget(&request_key);
+                    }
+                    return Ok(());
+                }
+            }
+            Err(e) => {
+                errors_channel
+                    .send(e)
+                    .await
+                    .expect("Unexpected error when sending to the error channel.");
+            }
+        }
+
+        self.acknowledge(received_message.ack_id).await
+    }
+}
+
+pub(crate) async fn spawn_pull_consumer_service(
+    subscription: String,
+    scraping_client: Client,
+    result_channel: PostalSender,
+    errors_tx: Sender<ScraperError>,
+    dedup_store: Data<DedupStore>,
+    domain_cooldowns: Data<DomainCooldowns>,
+    source_registry: Data<SourceRegistry>,
+) {
+    // CONSTANTS
+    let max_messages: usize = env_or("PULL_MAX_MESSAGES", 10);
+    let max_outstanding: usize = env_or("PULL_MAX_OUTSTANDING", 10);
+
+    // Logging
+    println!("Starting up pull consumer for {}.", subscription);
+
+    let pubsub_client = Client::new();
+    let consumer = PullConsumer {
+        endpoint: PubSubEndpoint::from_env(pubsub_client.clone(), errors_tx.clone()),
+        client: pubsub_client,
+        subscription,
+    };
+    let scraping_client = Data::new(scraping_client);
+    let result_channel = Data::new(result_channel);
+    let errors_channel = Data::new(errors_tx.clone());
+
+    // Flow control: a message holds a permit until it has been processed
+    let permits = Arc::new(Semaphore::new(max_outstanding.max(1)));
+
+    loop {
+        // Waiting for capacity before pulling, so that messages are not held past their deadline
+        let mut first_permit = Some(
+            permits
+                .clone()
+                .acquire_owned()
+                .await
+                .expect("Semaphore is never closed."),
+        );
+        let pull_size = (permits.available_permits() + 1).min(max_messages.max(1));
+
+        let received_messages = match consumer.pull(pull_size).await {
+            Ok(received_messages) => received_messages,
+            Err(e) => {
+                errors_tx
+                    .send(e)
+                    .await
+                    .expect("Unexpected error when sending to the error channel.");
+                tokio::time::sleep(PULL_RETRY_DELAY).await;
+                continue;
+            }
+        };
+
+        for received_message in received_messages {
+            let permit = match first_permit.take() {
+                Some(permit) => permit,
+                None => permits
+                    .clone()
+                    .acquire_owned()
+                    .await
+                    .expect("Semaphore is never closed."),
+            };
+            let consumer = consumer.clone();
+            let scraping_client = scraping_client.clone();
+            let result_channel = result_channel.clone();
+            let errors_channel = errors_channel.clone();
+            let dedup_store = dedup_store.clone();
+            let domain_cooldowns = domain_cooldowns.clone();
+            let source_registry = source_registry.clone();
+
+            tokio::task::spawn(async move {
+                let processed = consumer
+                    .process(
+                        received_message,
+                        scraping_client,
+                        result_channel,
+                        errors_channel.clone(),
+                        dedup_store,
+                        domain_cooldowns,
+                        source_registry,
+                    )
+                    .await;
+                if let Err(e) = processed {
+                    errors_channel
+                        .send(e)
+                        .await
+                        .expect("Unexpected error when sending to the error channel.");
+                }
+                drop(permit);
+            });
+        }
+    }
+}
@@ -0,0 +1,303 @@
+use std::{
+    path::PathBuf,
+    sync::{Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use jsonwebtoken::{jwk::JwkSet, Algorithm, DecodingKey, Validation};
+
+use crate::errors::AuthError;
+
+/// Issuers used by Google for the OIDC tokens attached to push requests.
+const GOOGLE_ISSUERS: [&str; 2] = ["https://accounts.google.com", "accounts.google.com"];
+
+/// Minimum time between two reloads of the JWKS file, as anyone can send an unknown key id.
+const JWKS_RELOAD_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, serde::Deserialize)]
+struct PushClaims {
+    email: Option<String>,
+    email_verified: Option<bool>,
+}
+
+/// Verifies the OIDC token that Pub/Sub attaches to push requests.
+/// Issuer, audience and expiry are checked by the JWT validation, and the token must belong to the
+/// configured service account.
+pub(crate) struct PushAuthenticator {
+    jwks_path: PathBuf,
+    jwks: RwLock<JwkSet>,
+    last_reload: Mutex<Instant>,
+    audience: String,
+    service_account_email: String,
+}
+
+impl PushAuthenticator {
+    /// Verification is enabled by setting `PUSH_AUTH_AUDIENCE`.
+    pub(crate) fn from_env() -> Option<Self> {
+        let audience = std::env::var("PUSH_AUTH_AUDIENCE").ok()?;
+        let service_account_email = std::env::var("PUSH_AUTH_SERVICE_ACCOUNT")
+            .expect("Missing PUSH_AUTH_SERVICE_ACCOUNT env variable.");
+        let jwks_path = PathBuf::from(
+            std::env::var("PUSH_AUTH_JWKS_PATH")
+                .expect("Missing PUSH_AUTH_JWKS_PATH env variable."),
+        );
+
+        Some(
+            PushAuthenticator::new(jwks_path, audience, service_account_email)
+                .expect("Failed to load the push auth JWKS."),
+        )
+    }
+
+    pub(crate) fn new(
+        jwks_path: PathBuf,
+        audience: String,
+        service_account_email: String,
+    ) -> Result<Self, AuthError> {
+        let jwks = load_jwks(&jwks_path)?;
+        Ok(PushAuthenticator {
+            jwks_path,
+            jwks: RwLock::new(jwks),
+            last_reload: Mutex::new(Instant::now()),
+            audience,
+            service_account_email,
+        })
+    }
+
+    /// Verifies the value of the `Authorization` header.
+    pub(crate) fn verify(&self, authorization: Option<&str>) -> Result<(), AuthError> {
+        let token = authorization
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(|| AuthError::new("Missing bearer token."))?;
+
+        // Finding the key the token was signed with
+        let header = jsonwebtoken::decode_header(token)?;
+        let kid = header
+            .kid
+            .ok_or_else(|| AuthError::new("Token is missing a key id."))?;
+        let decoding_key = self.find_decoding_key(&kid)?;
+
+        // Validating the signature, issuer, audience and expiry
+        // The algorithm is pinned to the one Google signs with, rather than read from the token.
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_audience(&[&self.audience]);
+        validation.set_issuer(&GOOGLE_ISSUERS);
+        validation.set_required_spec_claims(&["exp", "iss", "aud"]);
+        let claims = jsonwebtoken::decode::<PushClaims>(token, &decoding_key, &validation)?.claims;
+
+        // Checking the token belongs to the expected service account
+        match (claims.email, claims.email_verified) {
+            (Some(email), Some(true)) if email == self.service_account_email => Ok(()),
+            _ => Err(AuthError::new(
+                "Token was not issued to the expected service account.",
+            )),
+        }
+    }
+
+    /// Reloads the JWKS file when the key id is unknown, so that rotated keys are picked up
+    /// without a restart. Reloads are limited to one per `JWKS_RELOAD_INTERVAL`.
+    fn find_decoding_key(&self, kid: &str) -> Result<DecodingKey, AuthError> {
+        let unknown_key = || AuthError::new("Token was signed with an unknown key.");
+        if let Some(jwk) = self.jwks.read().expect("Poisoned JWKS lock.").find(kid) {
+            return Ok(DecodingKey::from_jwk(jwk)?);
+        }
+
+        // Claiming the reload, unless another one happened recently
+        {
+            let mut last_reload = self.last_reload.lock().expect("Poisoned JWKS reload lock.");
+            if last_reload.elapsed() < JWKS_RELOAD_INTERVAL {
+                return Err(unknown_key());
+            }
+            *last_reload = Instant::now();
+        }
+
+        let jwks = load_jwks(&self.jwks_path)?;
+        let decoding_key = match jwks.find(kid) {
+            Some(jwk) => DecodingKey::from_jwk(jwk)?,
+            None => return Err(unknown_key()),
+        };
+        *self.jwks.write().expect("Poisoned JWKS lock.") = jwks;
+
+        Ok(decoding_key)
+    }
+}
+
+/// Guards the admin endpoints with a static bearer token.
+pub(crate) struct AdminAuthenticator {
+    token: String,
+}
+
+impl AdminAuthenticator {
+    /// The admin endpoints are only served when `ADMIN_TOKEN` is set.
+    pub(crate) fn from_env() -> Option<Self> {
+        let token = std::env::var("ADMIN_TOKEN").ok()?;
+        if token.is_empty() {
+            panic!("Invalid ADMIN_TOKEN env variable: empty token.");
+        }
+        Some(AdminAuthenticator { token })
+    }
+
+    /// Verifies the value of the `Authorization` header.
+    pub(crate) fn verify(&self, authorization: Option<&str>) -> Result<(), AuthError> {
+        let token = authorization
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(|| AuthError::new("Missing bearer token."))?;
+
+        // Comparing every byte, so that the time taken does not reveal the matching prefix
+        let matches = token.len() == self.token.len()
+            && token
+                .bytes()
+                .zip(self.token.bytes())
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0;
+        match matches {
+            true => Ok(()),
+            false => Err(AuthError::new("Invalid admin token.")),
+        }
+    }
+}
+
+fn load_jwks(path: &PathBuf) -> Result<JwkSet, AuthError> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| AuthError::new(&format!("Failed to read JWKS file: {}", e)))?;
+    serde_json::from_str(&contents)
+        .map_err(|e| AuthError::new(&format!("Failed to parse JWKS file: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use jsonwebtoken::{Algorithm, EncodingKey, Header};
+
+    use super::{PushAuthenticator, JWKS_RELOAD_INTERVAL};
+
+    const AUDIENCE: &str = "https://scraper.example.com/scraping-request";
+    const SERVICE_ACCOUNT: &str = "pusher@project.iam.gserviceaccount.com";
+
+    fn authenticator() -> PushAuthenticator {
+        PushAuthenticator::new(
+            PathBuf::from("fixtures/push_auth/jwks.json"),
+            AUDIENCE.to_string(),
+            SERVICE_ACCOUNT.to_string(),
+        )
+        .unwrap()
+    }
+
+    fn now() -> i64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64
+    }
+
+    /// Claims of a valid push token, to be altered by each test.
+    fn claims() -> serde_json::Value {
+        serde_json::json!({
+            "iss": "https://accounts.google.com",
+            "aud": AUDIENCE,
+            "exp": now() + 3600,
+            "iat": now(),
+            "email": SERVICE_ACCOUNT,
+            "email_verified": true,
+        })
+    }
+
+    /// Signs the claims with the fixture key, returning an `Authorization` header value.
+    fn sign(claims: &serde_json::Value, kid: &str) -> String {
+        let key =
+            EncodingKey::from_rsa_pem(include_bytes!("../fixtures/push_auth/private-key.pem"))
+                .unwrap();
+        let header = Header {
+            kid: Some(kid.to_string()),
+            ..Header::new(Algorithm::RS256)
+        };
+        format!(
+            "Bearer {}",
+            jsonwebtoken::encode(&header, claims, &key).unwrap()
+        )
+    }
+
+    #[test]
+    fn accepts_valid_tokens() {
+        let token = sign(&claims(), "test-key");
+        assert!(authenticator().verify(Some(&token)).is_ok());
+    }
+
+    #[test]
+    fn rejects_missing_tokens() {
+        assert!(authenticator().verify(None).is_err());
+        assert!(authenticator().verify(Some("Basic abc")).is_err());
+    }
+
+    #[test]
+    fn rejects_tokens_for_another_audience() {
+        let mut claims = claims();
+        claims["aud"] = "https://elsewhere.example.com".into();
+        let token = sign(&claims, "test-key");
+        assert!(authenticator().verify(Some(&token)).is_err());
+    }
+
+    #[test]
+    fn rejects_expired_tokens() {
+        let mut claims = claims();
+        claims["exp"] = (now() - 3600).into();
+        let token = sign(&claims, "test-key");
+        assert!(authenticator().verify(Some(&token)).is_err());
+    }
+
+    #[test]
+    fn rejects_tokens_of_other_accounts() {
+        let mut claims = claims();
+        claims["email"] = "someone@example.com".into();
+        let token = sign(&claims, "test-key");
+        assert!(authenticator().verify(Some(&token)).is_err());
+
+        let mut claims = self::claims();
+        claims["email_verified"] = false.into();
+        let token = sign(&claims, "test-key");
+        assert!(authenticator().verify(Some(&token)).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_key_ids() {
+        let token = sign(&claims(), "rotated-key");
+        assert!(authenticator().verify(Some(&token)).is_err());
+    }
+
+    #[test]
+    fn reloads_rotated_keys_at_most_once_per_interval() {
+        // Starting without any key
+        let jwks_path = std::env::temp_dir().join(format!("jwks-{}.json", rand::random::<u64>()));
+        std::fs::write(&jwks_path, r#"{"keys": []}"#).unwrap();
+        let authenticator = PushAuthenticator::new(
+            jwks_path.clone(),
+            AUDIENCE.to_string(),
+            SERVICE_ACCOUNT.to_string(),
+        )
+        .unwrap();
+
+        // The new key is only read once the interval has elapsed
+        std::fs::copy("fixtures/push_auth/jwks.json", &jwks_path).unwrap();
+        let token = sign(&claims(), "test-key");
+        assert!(authenticator.verify(Some(&token)).is_err());
+        *authenticator.last_reload.lock().unwrap() -= JWKS_RELOAD_INTERVAL;
+        assert!(authenticator.verify(Some(&token)).is_ok());
+
+        std::fs::remove_file(jwks_path).unwrap();
+    }
+
+    #[test]
+    fn rejects_other_algorithms() {
+        // Signed with the public key material as an HMAC secret
+        let header = Header {
+            kid: Some("test-key".to_string()),
+            ..Header::new(Algorithm::HS256)
+        };
+        let key = EncodingKey::from_secret(include_bytes!("../fixtures/push_auth/jwks.json"));
+        let token = format!(
+            "Bearer {}",
+            jsonwebtoken::encode(&header, &claims(), &key).unwrap()
+        );
+        assert!(authenticator().verify(Some(&token)).is_err());
+    }
+}
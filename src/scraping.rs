@@ -8,27 +8,200 @@ pub mod results {
 pub mod json_results {
     use std::collections::HashMap;
 
-    use super::results::ScrapingResult;
+    use super::results::{
+        Availability, Money, Pricing, ScrapingFailure, ScrapingResult, ScrapingStatus,
+    };
 
-    #[derive(Debug, serde::Deserialize, serde::Serialize)]
+    #[derive(Debug, Clone, Copy, Default, serde::Deserialize, serde::Serialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum ScrapingStatusJson {
+        #[default]
+        Success,
+        Failure,
+    }
+
+    #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+    pub struct ScrapingFailureJson {
+        error_kind: String,
+        error_message: String,
+        attempts: u32,
+    }
+
+    #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+    pub struct MoneyJson {
+        amount_minor: i64,
+        currency: String,
+    }
+
+    impl From<Money> for MoneyJson {
+        fn from(value: Money) -> Self {
+            MoneyJson {
+                amount_minor: value.amount_minor,
+                currency: value.currency,
+            }
+        }
+    }
+
+    impl From<MoneyJson> for Money {
+        fn from(value: MoneyJson) -> Self {
+            Money {
+                amount_minor: value.amount_minor,
+                currency: value.currency,
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+    pub struct PricingJson {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        list_price: Option<MoneyJson>,
+        savings_percentage: u32,
+        deal_badge: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        coupon_amount: Option<MoneyJson>,
+        coupon_percentage: u32,
+    }
+
+    impl From<Pricing> for PricingJson {
+        fn from(value: Pricing) -> Self {
+            PricingJson {
+                list_price: value.list_price.map(Into::into),
+                savings_percentage: value.savings_percentage,
+                deal_badge: value.deal_badge,
+                coupon_amount: value.coupon_amount.map(Into::into),
+                coupon_percentage: value.coupon_percentage,
+            }
+        }
+    }
+
+    impl From<PricingJson> for Pricing {
+        fn from(value: PricingJson) -> Self {
+            Pricing {
+                list_price: value.list_price.map(Into::into),
+                savings_percentage: value.savings_percentage,
+                deal_badge: value.deal_badge,
+                coupon_amount: value.coupon_amount.map(Into::into),
+                coupon_percentage: value.coupon_percentage,
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+    pub struct AvailabilityJson {
+        availability_text: String,
+        in_stock: bool,
+        sold_by: String,
+        ships_from: String,
+        fulfilled_by_amazon: bool,
+    }
+
+    impl From<Availability> for AvailabilityJson {
+        fn from(value: Availability) -> Self {
+            AvailabilityJson {
+                availability_text: value.availability_text,
+                in_stock: value.in_stock,
+                sold_by: value.sold_by,
+                ships_from: value.ships_from,
+                fulfilled_by_amazon: value.fulfilled_by_amazon,
+            }
+        }
+    }
+
+    impl From<AvailabilityJson> for Availability {
+        fn from(value: AvailabilityJson) -> Self {
+            Availability {
+                availability_text: value.availability_text,
+                in_stock: value.in_stock,
+                sold_by: value.sold_by,
+                ships_from: value.ships_from,
+                fulfilled_by_amazon: value.fulfilled_by_amazon,
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
     pub struct ScrapingResultJson {
         source: String,
         utc_timestamp: u64,
         name: String,
         identifier: String,
         price: f32,
+        #[serde(default)]
+        status: ScrapingStatusJson,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        failure: Option<ScrapingFailureJson>,
+        #[serde(default)]
+        request_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        availability: Option<AvailabilityJson>,
+        #[serde(default)]
+        currency: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        money: Option<MoneyJson>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pricing: Option<PricingJson>,
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        image_url: String,
         attributes: HashMap<String, String>,
         metadata: HashMap<String, String>,
     }
 
     impl From<ScrapingResult> for ScrapingResultJson {
         fn from(value: ScrapingResult) -> Self {
+            let status = match value.status() {
+                ScrapingStatus::Success => ScrapingStatusJson::Success,
+                ScrapingStatus::Failure => ScrapingStatusJson::Failure,
+            };
+
             ScrapingResultJson {
                 source: value.source,
                 utc_timestamp: value.utc_timestamp,
                 name: value.name,
                 identifier: value.identifier,
                 price: value.price,
+                status,
+                failure: value.failure.map(|failure| ScrapingFailureJson {
+                    error_kind: failure.error_kind,
+                    error_message: failure.error_message,
+                    attempts: failure.attempts,
+                }),
+                request_id: value.request_id,
+                availability: value.availability.map(Into::into),
+                currency: value.currency,
+                money: value.money.map(Into::into),
+                pricing: value.pricing.map(Into::into),
+                image_url: value.image_url,
+                attributes: value.attributes,
+                metadata: value.metadata,
+            }
+        }
+    }
+
+    impl From<ScrapingResultJson> for ScrapingResult {
+        fn from(value: ScrapingResultJson) -> Self {
+            let status = match value.status {
+                ScrapingStatusJson::Success => ScrapingStatus::Success,
+                ScrapingStatusJson::Failure => ScrapingStatus::Failure,
+            };
+
+            ScrapingResult {
+                source: value.source,
+                utc_timestamp: value.utc_timestamp,
+                name: value.name,
+                identifier: value.identifier,
+                price: value.price,
+                status: status as i32,
+                failure: value.failure.map(|failure| ScrapingFailure {
+                    error_kind: failure.error_kind,
+                    error_message: failure.error_message,
+                    attempts: failure.attempts,
+                }),
+                request_id: value.request_id,
+                availability: value.availability.map(Into::into),
+                currency: value.currency,
+                money: value.money.map(Into::into),
+                pricing: value.pricing.map(Into::into),
+                image_url: value.image_url,
                 attributes: value.attributes,
                 metadata: value.metadata,
             }
@@ -1,13 +1,21 @@
 use std::{
+    collections::HashMap,
     ops::Div,
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use async_trait::async_trait;
-use reqwest::{Client, Request, Response};
+use reqwest::{Client, Request, Response, StatusCode, Url};
 use scraper::{Html, Node};
+use serde::de::DeserializeOwned;
 
-use crate::{errors::CssError, scraping};
+use crate::{
+    errors::{CssError, ErrorContext, ScraperError},
+    scraping,
+};
+
+/// Key under which the Pub/Sub message id is stamped into result metadata.
+pub const PUBSUB_MESSAGE_ID_KEY: &str = "pubsub_message_id";
 
 /// Common scraping functions that should be implemented across all structs.
 /// All methods implemented here should have a default implementation.
@@ -29,13 +37,69 @@ pub trait BaseTraits {
         Ok(node.to_owned())
     }
 
+    /// Returns the value of an attribute on the first element matching the selector.
+    /// Returns `None` if nothing matches or the element does not have the attribute.
+    fn find_css_attribute(
+        &self,
+        document: &Html,
+        selector_str: &str,
+        attribute: &str,
+    ) -> Result<Option<String>, CssError> {
+        // Creating the selector
+        let selector = scraper::Selector::parse(selector_str)?;
+
+        // Reading the attribute of the first matching element
+        let value = document
+            .select(&selector)
+            .next()
+            .and_then(|element| element.value().attr(attribute))
+            .map(|value| value.trim().to_string());
+
+        Ok(value.filter(|value| !value.is_empty()))
+    }
+
+    /// Returns the whitespace-normalised text of the first element matching the selector,
+    /// including the text of its descendants. Returns `None` if nothing matches or the text is empty.
+    fn find_element_text(
+        &self,
+        document: &Html,
+        selector_str: &str,
+    ) -> Result<Option<String>, CssError> {
+        // Creating the selector
+        let selector = scraper::Selector::parse(selector_str)?;
+
+        // Collecting the text within the first matching element
+        let text = document.select(&selector).next().map(|element| {
+            element
+                .text()
+                .flat_map(str::split_whitespace)
+                .collect::<Vec<_>>()
+                .join(" ")
+        });
+
+        Ok(text.filter(|text| !text.is_empty()))
+    }
+
+    /// Describes the product being scraped, attached to every error raised while scraping it.
+    fn error_context(&self) -> ErrorContext {
+        ErrorContext::default()
+    }
+
+    /// Whether a response with this error status should be retried.
+    /// Sources can opt out for statuses that signal a block, where retrying only makes things worse.
+    fn should_retry_status(&self, _status: StatusCode) -> bool {
+        true
+    }
+
     async fn request(
         &self,
         client: &Client,
         request: Request,
         max_retries: Option<u32>,
         exponential_backoff_algo: Option<fn(u32) -> u32>,
-    ) -> Result<Response, reqwest::Error> {
+    ) -> Result<Response, ScraperError> {
+        let context = self.error_context().with_url(request.url().as_str());
+
         // Configuring max retries
         let _max_retries = match max_retries {
             Some(i) if i > 10 => 10,
@@ -53,24 +117,52 @@ pub trait BaseTraits {
         let mut i = 0;
         while i < _max_retries {
             let request = request.try_clone().expect("Unreachable!"); // All request types should be cloneable in this case.
-            let result = client.execute(request).await?;
-            i += 1; // Incrementing the counter after making the request.
+            i += 1; // Incrementing the counter before making the request.
+            let result = client
+                .execute(request)
+                .await
+                .map_err(|e| ScraperError::from_request(context.clone(), e).with_attempts(i))?;
             match result.error_for_status() {
                 Ok(i) => {
                     return Ok(i);
                 }
-                Err(_) if i < _max_retries => {
+                Err(e)
+                    if i < _max_retries
+                        && e.status().is_none_or(|s| self.should_retry_status(s)) =>
+                {
                     let sleep_seconds = _exponential_backoff_algo(i);
                     let sleep_duration = Duration::from_secs(sleep_seconds.into());
                     tokio::time::sleep(sleep_duration).await;
                 }
-                Err(e) => return Err(e),
+                Err(e) => return Err(ScraperError::from_request(context, e).with_attempts(i)),
             }
         }
 
         unreachable!()
     }
 
+    /// Fetches a JSON document, retrying like `request`, and deserializes it.
+    async fn fetch_json<T: DeserializeOwned>(
+        &self,
+        client: &Client,
+        url: Url,
+    ) -> Result<T, ScraperError>
+    where
+        Self: Sized,
+    {
+        let context = self.error_context().with_url(url.as_str());
+        let request = client
+            .get(url)
+            .build()
+            .map_err(|e| ScraperError::from_request(context.clone(), e))?;
+
+        self.request(client, request, None, None)
+            .await?
+            .json::<T>()
+            .await
+            .map_err(|e| ScraperError::from_request(context, e))
+    }
+
     fn get_current_utc_time(&self) -> u64 {
         SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -84,12 +176,79 @@ pub trait Source {
 }
 
 #[async_trait]
-pub trait Scraper: BaseTraits + Source + Send {
+pub trait Scraper: BaseTraits + Source + Send + Sync {
     /// This method should return a unique identifier for the targeted scraping product.
     fn get_unique_id(&self) -> String;
 
+    /// The identifier reported on results for this request, e.g. the product code.
+    fn get_identifier(&self) -> String;
+
+    /// Domain the request is sent to, used to cool down domains that block the scraper.
+    /// Sources that do not make network requests return `None`.
+    fn get_target_domain(&self) -> Option<String> {
+        None
+    }
+
+    /// Optional caller-supplied key used to skip repeated requests. Empty keys are ignored.
+    fn get_idempotency_key(&self) -> Option<String>;
+
+    /// Id used to join results back to the request that produced them.
+    fn get_request_id(&self) -> String;
+
+    fn get_request_timestamp(&self) -> u64;
+
+    /// Request attributes, copied as-is onto every result.
+    fn get_attributes(&self) -> HashMap<String, String>;
+
+    fn get_metadata(&self) -> HashMap<String, String>;
+
+    /// The request metadata, stamped with the request timestamp.
+    fn get_result_metadata(&self) -> HashMap<String, String> {
+        let mut metadata = self.get_metadata();
+        metadata.insert(
+            "request_timestamp".to_string(),
+            self.get_request_timestamp().to_string(),
+        );
+        metadata
+    }
+
     async fn scrape(
         &self,
         client: &Client,
-    ) -> Result<scraping::results::ScrapingResult, Box<dyn std::error::Error + Send>>;
+    ) -> Result<scraping::results::ScrapingResult, ScraperError>;
+
+    /// Scrapes every result produced by the request. Sources that report several products per
+    /// request, e.g. one per variant, override this; the others produce the result of `scrape`.
+    async fn scrape_all(
+        &self,
+        client: &Client,
+    ) -> Result<Vec<scraping::results::ScrapingResult>, ScraperError> {
+        Ok(vec![self.scrape(client).await?])
+    }
+
+    /// Builds the result published in place of a successful one when scraping fails, so that
+    /// consumers can tell a failed scrape apart from one that has not happened yet.
+    fn get_failure_result(&self, error: &ScraperError) -> scraping::results::ScrapingResult {
+        scraping::results::ScrapingResult {
+            source: self.get_source_name(),
+            utc_timestamp: self.get_current_utc_time(),
+            name: String::new(),
+            identifier: self.get_identifier(),
+            price: 0.0,
+            status: scraping::results::ScrapingStatus::Failure as i32,
+            failure: Some(scraping::results::ScrapingFailure {
+                error_kind: error.kind().to_string(),
+                error_message: error.to_string(),
+                attempts: error.attempts(),
+            }),
+            request_id: self.get_request_id(),
+            availability: None,
+            currency: String::new(),
+            money: None,
+            pricing: None,
+            image_url: String::new(),
+            attributes: self.get_attributes(),
+            metadata: self.get_result_metadata(),
+        }
+    }
 }
@@ -1,94 +1,248 @@
-use std::error::Error;
-
 use actix_web::{
+    http::header::AUTHORIZATION,
     web::{Data, Json},
-    HttpResponse, Responder,
+    HttpRequest, HttpResponse, Responder,
 };
 use reqwest::Client;
 use tokio::sync::mpsc::Sender;
 
 use crate::{
-    pubsub::PubSubMessage, scraping::results::ScrapingResult, scraping_traits::Scraper, BoxedErr,
+    cooldown::DomainCooldowns,
+    dead_letter::DeadLetterStore,
+    dedup::DedupStore,
+    errors::ScraperError,
+    postal::PostalSender,
+    pubsub::PubSubMessage,
+    push_auth::{AdminAuthenticator, PushAuthenticator},
+    scraping::results::ScrapingResult,
+    scraping_traits::{Scraper, PUBSUB_MESSAGE_ID_KEY},
+    sources::SourceRegistry,
 };
 
 pub(crate) async fn hello_world() -> impl Responder {
     HttpResponse::Ok().body("Hello World!")
 }
 
+/// Verifies the admin token on requests to the admin endpoints.
+/// Returns the response rejecting the request, if the token is missing or wrong.
+fn reject_non_admin(
+    request: &HttpRequest,
+    admin_authenticator: &AdminAuthenticator,
+) -> Option<HttpResponse> {
+    let authorization = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+    admin_authenticator
+        .verify(authorization)
+        .err()
+        .map(|e| HttpResponse::Unauthorized().body(e.to_string()))
+}
+
+pub(crate) async fn dead_letters_handler(
+    request: HttpRequest,
+    admin_authenticator: Data<AdminAuthenticator>,
+    dead_letters: Data<DeadLetterStore>,
+) -> impl Responder {
+    if let Some(response) = reject_non_admin(&request, &admin_authenticator) {
+        return response;
+    }
+
+    match dead_letters.read_all().await {
+        Ok(records) => HttpResponse::Ok().json(records),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+pub(crate) async fn redrive_dead_letters_handler(
+    request: HttpRequest,
+    admin_authenticator: Data<AdminAuthenticator>,
+    dead_letters: Data<DeadLetterStore>,
+    result_channel: Data<PostalSender>,
+) -> impl Responder {
+    if let Some(response) = reject_non_admin(&request, &admin_authenticator) {
+        return response;
+    }
+
+    match dead_letters.redrive(&result_channel).await {
+        Ok(record_count) => HttpResponse::Ok().body(format!(
+            "Dead letters re-driven.\nNumber of results re-driven: {}",
+            record_count
+        )),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[allow(clippy::too_many_arguments)] // Each argument is an actix-web extractor.
 pub(crate) async fn scraping_request_handler(
+    request: HttpRequest,
     json_payload: Json<PubSubMessage>,
     request_client: Data<Client>,
-    result_channel: Data<Sender<ScrapingResult>>,
-    errors_channel: Data<Sender<BoxedErr>>,
+    result_channel: Data<PostalSender>,
+    errors_channel: Data<Sender<ScraperError>>,
+    push_authenticator: Data<Option<PushAuthenticator>>,
+    dedup_store: Data<DedupStore>,
+    domain_cooldowns: Data<DomainCooldowns>,
+    source_registry: Data<SourceRegistry>,
 ) -> impl Responder {
+    // Verifying the push token, if enabled
+    if let Some(push_authenticator) = push_authenticator.as_ref() {
+        let authorization = request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok());
+        if let Err(e) = push_authenticator.verify(authorization) {
+            return HttpResponse::Unauthorized().body(e.to_string());
+        }
+    }
+
     // Decoding the inner payload
     let payload = json_payload.into_inner();
 
+    let message_id = payload.message.get_message_id().map(str::to_string);
+
     // Unpacking the scraping requests
-    let scraping_requests = match payload.get_scraping_requests() {
+    let dispatched = match payload.get_scraping_requests(&source_registry) {
         Ok(i) => i,
         Err(e) => return HttpResponse::BadRequest().body::<String>(e.to_string()),
     };
+
+    // Acknowledging redelivered messages without scraping them again
+    if let Some(message_id) = &message_id {
+        if !dedup_store.insert_message_id(message_id) {
+            return HttpResponse::Ok().body(format!(
+                "Duplicate message acknowledged.\nMessage id: {}",
+                message_id
+            ));
+        }
+    }
+
+    // Reporting the requests for unknown sources
+    let rejected_count = dispatched.rejected.len();
+    for e in dispatched.rejected {
+        errors_channel
+            .send(e)
+            .await
+            .expect("Unexpected error when sending to the error channel.");
+    }
+
+    let scraping_requests = dedup_store.deduplicate_requests(dispatched.scrapers);
     let request_count = scraping_requests.len();
 
     // Spawning a separate async thread to execute the scraping requests
     tokio::task::spawn(async move {
         scraping_request(
             scraping_requests,
+            message_id,
             request_client,
             result_channel,
             errors_channel,
+            domain_cooldowns,
         )
         .await
     });
 
     let response_msg = format!(
-        "Scraping requests acknowleged.\nNumber of scraping requests recieved: {}",
-        request_count
+        "Scraping requests acknowleged.\nNumber of scraping requests recieved: {}\nNumber of scraping requests rejected: {}",
+        request_count, rejected_count
     );
     HttpResponse::Ok().body(response_msg)
 }
 
+/// Scrapes a single request into its results. Failed scrapes still produce a result, flagged as
+/// a failure, alongside the error.
+///
+/// Domains cooling down after serving a block page are skipped, and a blocked scrape starts
+/// a cool-down for its domain.
+async fn scrape_request(
+    req: Box<dyn Scraper + Send>,
+    client: &Client,
+    domain_cooldowns: &DomainCooldowns,
+) -> (Vec<ScrapingResult>, Option<ScraperError>) {
+    let scraped = match req.get_target_domain() {
+        Some(domain) => {
+            // Waiting for a permit, so that requests queued behind a blocked one see the cool-down
+            let _permit = domain_cooldowns.acquire(&domain).await;
+            match domain_cooldowns.remaining(&domain) {
+                Some(remaining) => Err(ScraperError::Blocked {
+                    context: req.error_context(),
+                    reason: format!(
+                        "{} is cooling down for another {}s.",
+                        domain,
+                        remaining.as_secs()
+                    ),
+                }),
+                None => {
+                    let scraped = req.scrape_all(client).await;
+                    if let Err(ScraperError::Blocked { .. }) = scraped {
+                        domain_cooldowns.block(&domain);
+                    }
+                    scraped
+                }
+            }
+        }
+        None => req.scrape_all(client).await,
+    };
+
+    match scraped {
+        Ok(results) => (results, None),
+        Err(e) => (vec![req.get_failure_result(&e)], Some(e)),
+    }
+}
+
+/// Returns `true` if every result produced was handed to the postal service.
 pub(crate) async fn scraping_request(
     scraping_requests: Vec<Box<dyn Scraper + Send>>,
+    message_id: Option<String>,
     request_client: Data<Client>,
-    result_channel: Data<Sender<ScrapingResult>>,
-    failed_channel: Data<Sender<BoxedErr>>,
-) -> () {
+    result_channel: Data<PostalSender>,
+    failed_channel: Data<Sender<ScraperError>>,
+    domain_cooldowns: Data<DomainCooldowns>,
+) -> bool {
     println!("Processing scraping request.");
+    let mut handed_off = true;
 
     let mut tasks = tokio::task::JoinSet::new();
     println!("Number of scraping requests: {}", scraping_requests.len());
     for req in scraping_requests.into_iter() {
         let client = request_client.clone();
+        let domain_cooldowns = domain_cooldowns.clone();
 
         // Spawning a separate task
-        tasks.spawn(async move {
-            let req = req.scrape(&client);
-            req.await
-        });
+        tasks.spawn(async move { scrape_request(req, &client, &domain_cooldowns).await });
     }
 
     while let Some(thread_res) = tasks.join_next().await {
         match thread_res {
-            Ok(res) => match res {
-                Ok(i) => {
-                    match result_channel.send(i).await {
+            Ok((results, error)) => {
+                for mut result in results {
+                    // Stamping the id of the Pub/Sub message that carried the request
+                    if let Some(message_id) = &message_id {
+                        result
+                            .metadata
+                            .insert(PUBSUB_MESSAGE_ID_KEY.to_string(), message_id.clone());
+                    }
+
+                    match result_channel.send(result).await {
                         Ok(_) => {}
                         Err(e) => {
-                            println!("Error occured when sending the ScrapingResult across the mpsc channel. See error:");
+                            handed_off = false;
+                            println!("Error occured when handing the ScrapingResult to the postal service. See error:");
                             println!("{}", e);
                         }
                     };
                 }
-                Err(e) => match failed_channel.send(e).await {
-                    Ok(_) => {}
-                    Err(internal_err) => {
-                        println!("Error occured when sending the Error raised during the scraping process across the mpsc channel. See error:");
-                        println!("{}", internal_err);
+
+                if let Some(e) = error {
+                    match failed_channel.send(e).await {
+                        Ok(_) => {}
+                        Err(internal_err) => {
+                            println!("Error occured when sending the Error raised during the scraping process across the mpsc channel. See error:");
+                            println!("{}", internal_err);
+                        }
                     }
-                },
-            },
+                }
+            }
             Err(e) => {
                 println!("JoinError encountered. See error below:");
                 println!("{}", e);
@@ -97,4 +251,5 @@ pub(crate) async fn scraping_request(
     }
 
     println!("Scraping request processed.");
+    handed_off
 }
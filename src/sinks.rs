@@ -0,0 +1,77 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use tokio::sync::mpsc::Sender;
+
+use crate::{
+    errors::{PublishError, ScraperError},
+    pubsub::PubSubEndpoint,
+    scraping::json_results::ScrapingResultJson,
+    sinks::{
+        jsonl_sink::JsonlSink, pubsub_sink::PubSubSink, stdout_sink::StdoutSink,
+        webhook_sink::WebhookSink,
+    },
+};
+
+pub mod error_sink;
+pub mod jsonl_sink;
+pub mod pubsub_sink;
+pub mod stdout_sink;
+pub mod webhook_sink;
+
+/// A destination that the postal service delivers batches of scraping results to.
+#[async_trait]
+pub(crate) trait ResultSink: Send + Sync {
+    fn get_sink_name(&self) -> String;
+
+    /// Delivers the whole batch. On error, the batch is retried as a whole if the error is retryable.
+    async fn publish(&self, results: &[ScrapingResultJson]) -> Result<(), PublishError>;
+}
+
+/// Builds a topic's publish url. A full url in `url_key` takes precedence over the project id in
+/// `PUBSUB_PROJECT_ID` and the topic id in `topic_id_key`.
+pub(crate) fn topic_endpoint_from_env(
+    endpoint: &PubSubEndpoint,
+    url_key: &str,
+    topic_id_key: &str,
+) -> String {
+    std::env::var(url_key).unwrap_or_else(|_| {
+        let project_id = std::env::var("PUBSUB_PROJECT_ID")
+            .unwrap_or_else(|_| panic!("Missing {} or PUBSUB_PROJECT_ID env variable.", url_key));
+        let topic_id = std::env::var(topic_id_key)
+            .unwrap_or_else(|_| panic!("Missing {} or {} env variable.", url_key, topic_id_key));
+        endpoint.url(&format!(
+            "projects/{}/topics/{}:publish",
+            project_id, topic_id
+        ))
+    })
+}
+
+/// Constructs the sink selected by the `RESULT_SINK` env variable, defaulting to Pub/Sub.
+pub(crate) fn result_sink_from_env(
+    client: Client,
+    errors_tx: Sender<ScraperError>,
+) -> Box<dyn ResultSink> {
+    let sink_type = std::env::var("RESULT_SINK").unwrap_or_else(|_| "pubsub".to_string());
+
+    match sink_type.as_str() {
+        "pubsub" => {
+            let endpoint = PubSubEndpoint::from_env(client.clone(), errors_tx);
+            let topic_endpoint =
+                topic_endpoint_from_env(&endpoint, "PUBLISH_TOPIC", "PUBSUB_TOPIC_ID");
+            Box::new(PubSubSink::new(client, endpoint, topic_endpoint))
+        }
+        "jsonl" => {
+            let path =
+                std::env::var("RESULT_SINK_PATH").expect("Missing RESULT_SINK_PATH env variable.");
+            Box::new(JsonlSink::open(&path).expect("Failed to open the JSONL result sink."))
+        }
+        "stdout" => Box::new(StdoutSink),
+        "webhook" => {
+            let url = std::env::var("RESULT_SINK_WEBHOOK_URL")
+                .expect("Missing RESULT_SINK_WEBHOOK_URL env variable.");
+            let bearer_token = std::env::var("RESULT_SINK_WEBHOOK_TOKEN").ok();
+            Box::new(WebhookSink::new(client, url, bearer_token))
+        }
+        other => panic!("Unsupported RESULT_SINK env variable: {}", other),
+    }
+}
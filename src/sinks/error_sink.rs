@@ -0,0 +1,59 @@
+use reqwest::Client;
+use tokio::sync::mpsc::Sender;
+
+use crate::{
+    errors::{ErrorRecord, PublishError, ScraperError},
+    pubsub::{OutboundPubSubPayload, PubSubEndpoint, PubSubMessageMessage},
+    sinks::{jsonl_sink::JsonlSink, pubsub_sink::PubSubSink, topic_endpoint_from_env},
+};
+
+/// Destination for the errors forwarded by the error handler service.
+pub(crate) enum ErrorSink {
+    Stderr,
+    Jsonl(JsonlSink),
+    PubSub(PubSubSink),
+}
+
+impl ErrorSink {
+    /// Constructs the sink selected by the `ERROR_SINK` env variable, defaulting to stderr.
+    pub(crate) fn from_env(client: Client, errors_tx: Sender<ScraperError>) -> Self {
+        let sink_type = std::env::var("ERROR_SINK").unwrap_or_else(|_| "stderr".to_string());
+
+        match sink_type.as_str() {
+            "stderr" => ErrorSink::Stderr,
+            "jsonl" => {
+                let path = std::env::var("ERROR_SINK_PATH")
+                    .expect("Missing ERROR_SINK_PATH env variable.");
+                ErrorSink::Jsonl(
+                    JsonlSink::open(&path).expect("Failed to open the JSONL error sink."),
+                )
+            }
+            "pubsub" => {
+                let endpoint = PubSubEndpoint::from_env(client.clone(), errors_tx);
+                let topic_endpoint =
+                    topic_endpoint_from_env(&endpoint, "ERROR_PUBLISH_TOPIC", "ERROR_TOPIC_ID");
+                ErrorSink::PubSub(PubSubSink::new(client, endpoint, topic_endpoint))
+            }
+            other => panic!("Unsupported ERROR_SINK env variable: {}", other),
+        }
+    }
+
+    pub(crate) async fn publish(&self, records: &[ErrorRecord]) -> Result<(), PublishError> {
+        match self {
+            ErrorSink::Stderr => {
+                for record in records {
+                    eprintln!("Error: {}", serde_json::to_string(record)?);
+                }
+                Ok(())
+            }
+            ErrorSink::Jsonl(jsonl_sink) => jsonl_sink.append(records).await,
+            ErrorSink::PubSub(pubsub_sink) => {
+                let payload = records
+                    .iter()
+                    .map(PubSubMessageMessage::from_json)
+                    .collect::<Result<OutboundPubSubPayload, _>>()?;
+                pubsub_sink.publish_payload(&payload).await
+            }
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use async_trait::async_trait;
+use tokio::{fs::File, io::AsyncWriteExt, sync::Mutex};
+
+use crate::{errors::PublishError, scraping::json_results::ScrapingResultJson, sinks::ResultSink};
+
+/// Appends each result as a single JSON line to a local file.
+pub(crate) struct JsonlSink {
+    file: Mutex<File>,
+}
+
+impl JsonlSink {
+    pub(crate) fn open(path: &str) -> std::io::Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+
+        Ok(JsonlSink {
+            file: Mutex::new(File::from_std(file)),
+        })
+    }
+
+    /// Appends any JSON-serializable records. Also used to write records other than results.
+    pub(crate) async fn append<T: serde::Serialize>(
+        &self,
+        records: &[T],
+    ) -> Result<(), PublishError> {
+        let mut lines = String::new();
+        for record in records {
+            let line = serde_json::to_string(record)?;
+            lines.push_str(&line);
+            lines.push('\n');
+        }
+
+        // Writing the whole batch at once so that concurrent writers never interleave lines
+        let mut file = self.file.lock().await;
+        file.write_all(lines.as_bytes()).await?;
+        file.flush().await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ResultSink for JsonlSink {
+    fn get_sink_name(&self) -> String {
+        "jsonl".to_string()
+    }
+
+    async fn publish(&self, results: &[ScrapingResultJson]) -> Result<(), PublishError> {
+        self.append(results).await
+    }
+}
@@ -0,0 +1,71 @@
+use async_trait::async_trait;
+use reqwest::Client;
+
+use crate::{
+    errors::PublishError,
+    pubsub::{OutboundPubSubPayload, PubSubEndpoint},
+    scraping::json_results::ScrapingResultJson,
+    sinks::ResultSink,
+};
+
+/// Publishes results to a Google Pub/Sub topic through its REST endpoint.
+pub(crate) struct PubSubSink {
+    client: Client,
+    endpoint: PubSubEndpoint,
+    topic_endpoint: String,
+}
+
+impl PubSubSink {
+    pub(crate) fn new(client: Client, endpoint: PubSubEndpoint, topic_endpoint: String) -> Self {
+        PubSubSink {
+            client,
+            endpoint,
+            topic_endpoint,
+        }
+    }
+
+    /// Publishes an already encoded payload. Also used to publish records other than results.
+    pub(crate) async fn publish_payload(
+        &self,
+        payload: &OutboundPubSubPayload,
+    ) -> Result<(), PublishError> {
+        // Serializing the payload
+        let serialized_payload = payload.serialize_payload()?;
+
+        // Authorising the request
+        // Failing to get a token is usually a transient metadata server issue.
+        let request = self
+            .client
+            .post(&self.topic_endpoint)
+            .header("Content-Type", "application/json");
+        let request = self
+            .endpoint
+            .authorize(request)
+            .await
+            .map_err(PublishError::retryable)?;
+
+        let response = request.body(serialized_payload).send().await?;
+        let response = PublishError::check_response(response)?.text().await?;
+
+        // Simply logging the response for now
+        println!("Pub/Sub publish response: {}", response);
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ResultSink for PubSubSink {
+    fn get_sink_name(&self) -> String {
+        "pubsub".to_string()
+    }
+
+    async fn publish(&self, results: &[ScrapingResultJson]) -> Result<(), PublishError> {
+        let payload = results
+            .iter()
+            .map(|result| result.encode_to_pubsub())
+            .collect::<OutboundPubSubPayload>();
+
+        self.publish_payload(&payload).await
+    }
+}
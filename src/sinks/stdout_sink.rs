@@ -0,0 +1,25 @@
+use std::io::Write;
+
+use async_trait::async_trait;
+
+use crate::{errors::PublishError, scraping::json_results::ScrapingResultJson, sinks::ResultSink};
+
+/// Prints each result as a single JSON line to stdout.
+pub(crate) struct StdoutSink;
+
+#[async_trait]
+impl ResultSink for StdoutSink {
+    fn get_sink_name(&self) -> String {
+        "stdout".to_string()
+    }
+
+    async fn publish(&self, results: &[ScrapingResultJson]) -> Result<(), PublishError> {
+        let mut stdout = std::io::stdout().lock();
+        for result in results {
+            let line = serde_json::to_string(result)?;
+            writeln!(stdout, "{}", line)?;
+        }
+
+        Ok(())
+    }
+}
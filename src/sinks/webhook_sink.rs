@@ -0,0 +1,40 @@
+use async_trait::async_trait;
+use reqwest::Client;
+
+use crate::{errors::PublishError, scraping::json_results::ScrapingResultJson, sinks::ResultSink};
+
+/// Posts each batch as a JSON array to a generic HTTP endpoint.
+pub(crate) struct WebhookSink {
+    client: Client,
+    url: String,
+    bearer_token: Option<String>,
+}
+
+impl WebhookSink {
+    pub(crate) fn new(client: Client, url: String, bearer_token: Option<String>) -> Self {
+        WebhookSink {
+            client,
+            url,
+            bearer_token,
+        }
+    }
+}
+
+#[async_trait]
+impl ResultSink for WebhookSink {
+    fn get_sink_name(&self) -> String {
+        "webhook".to_string()
+    }
+
+    async fn publish(&self, results: &[ScrapingResultJson]) -> Result<(), PublishError> {
+        let mut request = self.client.post(&self.url).json(results);
+        if let Some(bearer_token) = &self.bearer_token {
+            request = request.header("Authorization", format!("Bearer {}", bearer_token));
+        }
+
+        let response = request.send().await?;
+        PublishError::check_response(response)?;
+
+        Ok(())
+    }
+}
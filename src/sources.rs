@@ -1,2 +1,338 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use reqwest::Client;
+
+use crate::{
+    errors::{ErrorContext, ScraperError},
+    scraping::{
+        requests::{scraping_request::Source as RequestSource, ScrapingRequest},
+        results::ScrapingResult,
+    },
+    scraping_traits::{self, BaseTraits, Scraper},
+};
+
+#[cfg(not(any(
+    feature = "source-amzn",
+    feature = "source-generic",
+    feature = "source-json-ld",
+    feature = "source-shopify",
+    feature = "source-test",
+    feature = "source-woocommerce"
+)))]
+compile_error!("At least one source-* feature must be enabled.");
+
+/// Implements the `Scraper` accessors of the fields every request message has:
+/// `idempotency_key`, `request_id`, `request_timestamp`, `attributes` and `metadata`.
+macro_rules! request_accessors {
+    () => {
+        fn get_idempotency_key(&self) -> Option<String> {
+            Some(self.idempotency_key.to_owned()).filter(|key| !key.is_empty())
+        }
+
+        fn get_request_id(&self) -> String {
+            self.request_id.to_owned()
+        }
+
+        fn get_request_timestamp(&self) -> u64 {
+            self.request_timestamp
+        }
+
+        fn get_attributes(&self) -> std::collections::HashMap<String, String> {
+            self.attributes.clone()
+        }
+
+        fn get_metadata(&self) -> std::collections::HashMap<String, String> {
+            self.metadata.clone()
+        }
+    };
+}
+
+#[cfg(feature = "source-amzn")]
 pub mod amzn_source;
+#[cfg(feature = "source-generic")]
+pub mod generic_source;
+#[cfg(feature = "source-json-ld")]
+pub mod json_ld_source;
+#[cfg(feature = "source-shopify")]
+pub mod shopify_source;
+#[cfg(feature = "source-test")]
 pub mod test_source;
+#[cfg(feature = "source-woocommerce")]
+pub mod woocommerce_source;
+
+/// Builds a scraper from a request variant, returning `None` for variants of other sources.
+pub(crate) type SourceFactory = fn(RequestSource) -> Option<Box<dyn Scraper + Send>>;
+
+/// Maps source names to the factories of the sources compiled into this build.
+/// Each source is gated by a `source-*` cargo feature and registers itself when enabled.
+pub(crate) struct SourceRegistry {
+    factories: HashMap<&'static str, SourceFactory>,
+}
+
+/// Requests that were dispatched to a source, along with the errors of those for unknown sources.
+pub(crate) struct DispatchedRequests {
+    pub(crate) scrapers: Vec<Box<dyn Scraper + Send>>,
+    pub(crate) rejected: Vec<ScraperError>,
+}
+
+impl SourceRegistry {
+    pub(crate) fn new() -> Self {
+        SourceRegistry {
+            factories: HashMap::new(),
+        }
+    }
+
+    /// Registry holding every source enabled in this build.
+    pub(crate) fn with_enabled_sources() -> Self {
+        let mut registry = SourceRegistry::new();
+        #[cfg(feature = "source-amzn")]
+        amzn_source::register(&mut registry);
+        #[cfg(feature = "source-generic")]
+        generic_source::register(&mut registry);
+        #[cfg(feature = "source-json-ld")]
+        json_ld_source::register(&mut registry);
+        #[cfg(feature = "source-shopify")]
+        shopify_source::register(&mut registry);
+        #[cfg(feature = "source-test")]
+        test_source::register(&mut registry);
+        #[cfg(feature = "source-woocommerce")]
+        woocommerce_source::register(&mut registry);
+        registry
+    }
+
+    pub(crate) fn register(&mut self, name: &'static str, factory: SourceFactory) {
+        self.factories.insert(name, factory);
+    }
+
+    pub(crate) fn source_names(&self) -> Vec<&'static str> {
+        let mut names = self.factories.keys().copied().collect::<Vec<_>>();
+        names.sort_unstable();
+        names
+    }
+
+    /// Builds a scraper for the request from the factory registered under its source name.
+    /// Requests for sources that are not enabled get a scraper that fails, so that they still
+    /// produce a failure result.
+    pub(crate) fn build(
+        &self,
+        request: ScrapingRequest,
+    ) -> Result<Box<dyn Scraper + Send>, ScraperError> {
+        // Requests for sources added to the proto after this build was made decode without a source
+        let source = request.source.ok_or_else(|| ScraperError::InvalidRequest {
+            context: ErrorContext::default(),
+            reason: "Unknown source.".to_string(),
+        })?;
+
+        let name = source_name(&source);
+        let disabled = || ScraperError::InvalidRequest {
+            context: ErrorContext {
+                source: Some(name.to_string()),
+                ..Default::default()
+            },
+            reason: format!("Source {:?} is not enabled in this build.", name),
+        };
+        let Some(factory) = self.factories.get(name) else {
+            return Ok(Box::new(DisabledSource::new(source)));
+        };
+        factory(source).ok_or_else(disabled)
+    }
+
+    pub(crate) fn dispatch(&self, requests: Vec<ScrapingRequest>) -> DispatchedRequests {
+        let mut dispatched = DispatchedRequests {
+            scrapers: Vec::new(),
+            rejected: Vec::new(),
+        };
+        for request in requests {
+            match self.build(request) {
+                Ok(scraper) => dispatched.scrapers.push(scraper),
+                Err(e) => dispatched.rejected.push(e),
+            }
+        }
+        dispatched
+    }
+}
+
+/// Stands in for a request whose source is not enabled in this build.
+struct DisabledSource {
+    name: &'static str,
+    identifier: String,
+    idempotency_key: String,
+    request_id: String,
+    request_timestamp: u64,
+    attributes: HashMap<String, String>,
+    metadata: HashMap<String, String>,
+}
+
+impl DisabledSource {
+    fn new(source: RequestSource) -> Self {
+        let name = source_name(&source);
+        macro_rules! from_request {
+            ($request:ident, $identifier:expr) => {
+                DisabledSource {
+                    name,
+                    identifier: $identifier,
+                    idempotency_key: $request.idempotency_key,
+                    request_id: $request.request_id,
+                    request_timestamp: $request.request_timestamp,
+                    attributes: $request.attributes,
+                    metadata: $request.metadata,
+                }
+            };
+        }
+
+        // Reporting the identifier each source would have reported
+        let or_url = |identifier: &str, url: &str| match identifier.is_empty() {
+            true => url.to_string(),
+            false => identifier.to_string(),
+        };
+        let mut disabled_source = match source {
+            RequestSource::Test(request) => from_request!(request, request.content.clone()),
+            RequestSource::Amzn(request) => from_request!(request, request.product_code.clone()),
+            RequestSource::Generic(request) => {
+                from_request!(request, or_url(&request.identifier, &request.url))
+            }
+            RequestSource::JsonLd(request) => {
+                from_request!(request, or_url(&request.identifier, &request.url))
+            }
+            RequestSource::Shopify(request) => from_request!(
+                request,
+                match request.variant_id {
+                    0 => request.handle.clone(),
+                    variant_id => format!("{}:{}", request.handle, variant_id),
+                }
+            ),
+            RequestSource::WooCommerce(request) => from_request!(
+                request,
+                match request.product_id {
+                    0 => request.slug.clone(),
+                    product_id => product_id.to_string(),
+                }
+            ),
+        };
+        fill_request_id(&mut disabled_source.request_id);
+        disabled_source
+    }
+}
+
+impl scraping_traits::Source for DisabledSource {
+    fn get_source_name(&self) -> String {
+        self.name.to_string()
+    }
+}
+
+impl BaseTraits for DisabledSource {
+    fn error_context(&self) -> ErrorContext {
+        ErrorContext::product(self.name.to_string(), self.identifier.to_owned())
+    }
+}
+
+#[async_trait]
+impl Scraper for DisabledSource {
+    fn get_unique_id(&self) -> String {
+        format!("{} - {}", self.name, self.identifier)
+    }
+
+    fn get_identifier(&self) -> String {
+        self.identifier.to_owned()
+    }
+
+    request_accessors!();
+
+    async fn scrape(&self, _client: &Client) -> Result<ScrapingResult, ScraperError> {
+        Err(ScraperError::InvalidRequest {
+            context: self.error_context(),
+            reason: format!("Source {:?} is not enabled in this build.", self.name),
+        })
+    }
+}
+
+/// Name under which the source of each request variant registers itself.
+fn source_name(source: &RequestSource) -> &'static str {
+    match source {
+        RequestSource::Test(_) => "test",
+        RequestSource::Amzn(_) => "amzn",
+        RequestSource::Generic(_) => "generic",
+        RequestSource::JsonLd(_) => "json_ld",
+        RequestSource::Shopify(_) => "shopify",
+        RequestSource::WooCommerce(_) => "woocommerce",
+    }
+}
+
+/// English availability texts that mean the product cannot currently be bought, in lower case.
+/// Includes the schema.org `OutOfStock`/`SoldOut` values often found in attributes.
+#[cfg(any(feature = "source-amzn", feature = "source-generic"))]
+pub(crate) const OUT_OF_STOCK_MARKERS: &[&str] = &[
+    "out of stock",
+    "outofstock",
+    "sold out",
+    "soldout",
+    "unavailable",
+    "not available",
+];
+
+/// Parses the root url of a store, ending with a slash so that API paths can be joined to it.
+/// Store urls without a scheme are reached over https.
+#[cfg(any(feature = "source-shopify", feature = "source-woocommerce"))]
+pub(crate) fn parse_store_url(store_url: &str) -> Result<reqwest::Url, String> {
+    let store_url = store_url.trim().trim_end_matches('/');
+    let store_url = match store_url.contains("://") {
+        true => format!("{}/", store_url),
+        false => format!("https://{}/", store_url),
+    };
+    reqwest::Url::parse(&store_url).map_err(|e| e.to_string())
+}
+
+/// Generates a random request id for requests that arrive without one.
+pub(crate) fn fill_request_id(request_id: &mut String) {
+    if request_id.is_empty() {
+        *request_id = format!("{:032x}", rand::random::<u128>());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Client;
+
+    use super::SourceRegistry;
+    use crate::{
+        errors::ScraperError,
+        scraping::{
+            requests::{scraping_request::Source as RequestSource, ScrapingRequest, Shopify},
+            results::ScrapingStatus,
+        },
+    };
+
+    #[tokio::test]
+    async fn reports_requests_for_disabled_sources_as_failures() {
+        let request = ScrapingRequest {
+            source: Some(RequestSource::Shopify(Shopify {
+                handle: "hoodie".to_string(),
+                variant_id: 41,
+                request_id: "request-1".to_string(),
+                request_timestamp: 1_700_000_000,
+                attributes: [("sku".to_string(), "woo-hoodie".to_string())].into(),
+                ..Default::default()
+            })),
+        };
+        let scraper = SourceRegistry::new().build(request).unwrap();
+        let error = scraper.scrape(&Client::new()).await.unwrap_err();
+        assert!(matches!(error, ScraperError::InvalidRequest { .. }));
+        let result = scraper.get_failure_result(&error);
+
+        assert_eq!(result.source, "shopify");
+        assert_eq!(result.identifier, "hoodie:41");
+        assert_eq!(result.status, ScrapingStatus::Failure as i32);
+        assert_eq!(result.failure.unwrap().error_kind, "invalid_request");
+        assert_eq!(result.request_id, "request-1");
+        assert_eq!(result.attributes["sku"], "woo-hoodie");
+        assert_eq!(result.metadata["request_timestamp"], "1700000000");
+    }
+
+    #[test]
+    fn rejects_requests_without_a_source() {
+        let dispatched = SourceRegistry::new().dispatch(vec![ScrapingRequest { source: None }]);
+        assert!(dispatched.scrapers.is_empty());
+        assert_eq!(dispatched.rejected.len(), 1);
+    }
+}
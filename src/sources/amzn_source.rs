@@ -1,25 +1,36 @@
-use std::{collections::HashMap, error::Error};
-
 use async_trait::async_trait;
-use reqwest::{Client, Request};
+use reqwest::{Client, Request, StatusCode};
 use scraper::Html;
 
+use self::marketplace::Marketplace;
 use crate::{
-    errors::CssError,
-    scraping::{self, requests::Amzn, results::ScrapingResult},
-    scraping_traits::{self, BaseTraits, Source},
+    errors::{CssError, ErrorContext, ScraperError},
+    price::Money,
+    scraping::{
+        self,
+        requests::{scraping_request::Source as RequestSource, Amzn},
+        results::{Availability, Pricing, ScrapingResult, ScrapingStatus},
+    },
+    scraping_traits::{self, BaseTraits, Scraper, Source},
+    sources::{fill_request_id, SourceRegistry, OUT_OF_STOCK_MARKERS},
 };
 
-type BoxedErr = Box<dyn Error + Send>;
+mod marketplace;
 
 impl Amzn {
-    fn construct_request(&self, client: &Client) -> reqwest::Result<Request> {
+    fn construct_request(
+        &self,
+        client: &Client,
+        marketplace: &Marketplace,
+    ) -> Result<Request, ScraperError> {
         // Constructing the target url
         let product_code = self.get_product_asin_code();
-        let target_url = format!("https://www.amazon.sg/dp/{}", product_code);
+        let target_url = marketplace.product_url(&product_code);
 
         // Constructing the request
-        let request = client.get(target_url).build()?;
+        let request = client.get(&target_url).build().map_err(|e| {
+            ScraperError::from_request(self.error_context().with_url(&target_url), e)
+        })?;
 
         Ok(request)
     }
@@ -28,12 +39,37 @@ impl Amzn {
         self.product_code.to_owned()
     }
 
-    fn get_product_information(&self, document: &Html) -> Result<ScrapingResult, BoxedErr> {
+    fn get_marketplace(&self) -> Result<&'static Marketplace, ScraperError> {
+        Marketplace::find(&self.marketplace).ok_or_else(|| ScraperError::InvalidRequest {
+            context: self.error_context(),
+            reason: format!("Unsupported marketplace {:?}.", self.marketplace),
+        })
+    }
+
+    fn get_product_information(
+        &self,
+        document: &Html,
+        marketplace: &Marketplace,
+    ) -> Result<ScrapingResult, ScraperError> {
         // Getting the product_title
         let name = self.get_product_title(document)?;
 
+        // Getting the availability and offer details
+        let availability = self.get_product_availability(document, marketplace)?;
+
         // Getting product price
-        let price = self.get_product_price(document)?;
+        // Out of stock listings have no buy box price, so any other price on the page would be misleading.
+        let money = self.get_product_price(document, availability.in_stock, marketplace)?;
+        if money.is_none() && availability.in_stock {
+            return Err(ScraperError::Parse {
+                context: self.error_context(),
+                selector: GENERIC_PRICE_SELECTOR_STR.to_string(),
+                source: CssError::new("Failed to find Css Node."),
+            });
+        }
+
+        // Getting the list price and any discounts
+        let pricing = self.get_product_pricing(document, marketplace, money.as_ref())?;
 
         // Getting current timestamp
         let utc_timestamp = self.get_current_utc_time();
@@ -44,55 +80,350 @@ impl Amzn {
         // Getting unique id
         let identifier = self.get_product_asin_code();
 
+        // Carrying the request's correlation details over to the result
+        let request_id = self.get_request_id();
+        let attributes = self.get_attributes();
+        let mut metadata = self.get_result_metadata();
+        metadata.insert("marketplace".to_string(), marketplace.code.to_string());
+
         // Constructing the ScrapingResult
         let result = ScrapingResult {
             source,
             utc_timestamp,
             name,
             identifier,
-            price,
-            attributes: HashMap::new(),
-            metadata: HashMap::new(),
+            price: money.as_ref().map_or(0.0, Money::as_f32),
+            status: ScrapingStatus::Success as i32,
+            failure: None,
+            request_id,
+            availability: Some(availability),
+            currency: marketplace.currency.to_string(),
+            money: money.map(Into::into),
+            pricing: Some(pricing),
+            image_url: String::new(),
+            attributes,
+            metadata,
         };
 
         Ok(result)
     }
 
-    fn get_product_title(&self, document: &Html) -> Result<String, BoxedErr> {
-        const PRODUCT_TITLE_SELECTOR_STR: &str = "#productTitle";
+    /// Finds the first text node matching the selector.
+    fn find_text(&self, document: &Html, selector_str: &str) -> Result<String, ScraperError> {
+        let parse_error = |source| ScraperError::Parse {
+            context: self.error_context(),
+            selector: selector_str.to_string(),
+            source,
+        };
 
-        if let scraper::Node::Text(txt) =
-            self.find_css_node(&document, PRODUCT_TITLE_SELECTOR_STR)?
+        if let scraper::Node::Text(txt) = self
+            .find_css_node(document, selector_str)
+            .map_err(parse_error)?
         {
-            Ok(txt.trim().to_string())
+            Ok(txt.to_string())
         } else {
-            Err(CssError::new("Invalid Node found.").into())
+            Err(parse_error(CssError::new("Invalid Node found.")))
         }
     }
 
-    fn get_product_price(&self, document: &Html) -> Result<f32, BoxedErr> {
-        const PRODUCT_PRICE_SELECTOR_STR: &str = ".a-offscreen"; // Contains a text string of the price (e.g. $75.99)
+    /// Returns the text of the first selector that matches, trying them in order.
+    fn find_first_text(
+        &self,
+        document: &Html,
+        selector_strs: &[&str],
+    ) -> Result<Option<String>, ScraperError> {
+        for selector_str in selector_strs {
+            let text = self
+                .find_element_text(document, selector_str)
+                .map_err(|source| ScraperError::Parse {
+                    context: self.error_context(),
+                    selector: selector_str.to_string(),
+                    source,
+                })?;
+            if text.is_some() {
+                return Ok(text);
+            }
+        }
 
-        if let scraper::Node::Text(txt) =
-            self.find_css_node(&document, PRODUCT_PRICE_SELECTOR_STR)?
-        {
-            let cleaned_txt = txt
-                .trim()
-                .replace("$", "")
-                .replace(",", "")
-                .replace("S", "")
-                .replace("\"", "");
-
-            let price = cleaned_txt
-                .parse::<f32>()
-                .map_err(|e| Box::new(e) as BoxedErr)?;
-            Ok(price)
+        Ok(None)
+    }
+
+    fn get_product_title(&self, document: &Html) -> Result<String, ScraperError> {
+        const PRODUCT_TITLE_SELECTOR_STR: &str = "#productTitle";
+
+        let txt = self.find_text(document, PRODUCT_TITLE_SELECTOR_STR)?;
+        Ok(txt.trim().to_string())
+    }
+
+    /// Returns `None` if the page has no price. Prices outside the buy box are only considered
+    /// when the product is in stock.
+    fn get_product_price(
+        &self,
+        document: &Html,
+        in_stock: bool,
+        marketplace: &Marketplace,
+    ) -> Result<Option<Money>, ScraperError> {
+        // Buy box prices, across the page layouts in use
+        // The price to pay is listed first, as the buy box also holds list and per-unit prices.
+        const BUY_BOX_PRICE_SELECTOR_STRS: &[&str] = &[
+            "#corePriceDisplay_desktop_feature_div .priceToPay .a-offscreen",
+            "#corePrice_feature_div .priceToPay .a-offscreen",
+            "#apex_desktop .priceToPay .a-offscreen",
+            "#apex_desktop .apexPriceToPay .a-offscreen",
+            "#corePrice_feature_div .a-offscreen",
+            "#corePriceDisplay_desktop_feature_div .a-offscreen",
+            "#apex_desktop .a-offscreen",
+        ];
+
+        let txt = match self.find_first_text(document, BUY_BOX_PRICE_SELECTOR_STRS)? {
+            Some(txt) => txt,
+            None if in_stock => {
+                match self.find_first_text(document, &[GENERIC_PRICE_SELECTOR_STR])? {
+                    Some(txt) => txt,
+                    None => return Ok(None),
+                }
+            }
+            None => return Ok(None),
+        };
+        self.parse_money(&txt, marketplace).map(Some)
+    }
+
+    fn parse_money(&self, txt: &str, marketplace: &Marketplace) -> Result<Money, ScraperError> {
+        marketplace
+            .parse_price(txt)
+            .ok_or_else(|| ScraperError::PriceFormat {
+                context: self.error_context(),
+                raw: txt.trim().to_string(),
+            })
+    }
+
+    fn get_product_pricing(
+        &self,
+        document: &Html,
+        marketplace: &Marketplace,
+        current_price: Option<&Money>,
+    ) -> Result<Pricing, ScraperError> {
+        const LIST_PRICE_SELECTOR_STRS: &[&str] = &[
+            "#corePriceDisplay_desktop_feature_div .basisPrice .a-offscreen",
+            "#corePrice_desktop .a-text-price[data-a-strike=\"true\"] .a-offscreen",
+            "#corePrice_feature_div .a-text-price[data-a-strike=\"true\"] .a-offscreen",
+            "#apex_desktop .basisPrice .a-offscreen",
+            "#listPrice",
+            "#priceblock_listprice",
+        ];
+        const SAVINGS_SELECTOR_STRS: &[&str] = &[
+            "#corePriceDisplay_desktop_feature_div .savingsPercentage", // e.g. "-25%"
+            "#corePrice_desktop .savingsPercentage",
+            "#apex_desktop .savingsPercentage",
+            "#regularprice_savings",
+        ];
+        const DEAL_BADGE_SELECTOR_STRS: &[&str] = &[
+            "#dealBadge_feature_div .dealBadge",
+            "#dealBadge_feature_div",
+            "#dealBadgeSupportingText",
+        ];
+        const COUPON_SELECTOR_STRS: &[&str] = &[
+            "#promoPriceBlockMessage_feature_div .couponLabelText", // e.g. "Apply S$5.00 coupon"
+            "#couponBadgeRegularVpc",
+            "#vpcButton .a-color-success",
+        ];
+
+        // Getting the list price
+        // Prices that cannot be parsed are left out rather than failing the whole scrape.
+        let list_price = self
+            .find_first_text(document, LIST_PRICE_SELECTOR_STRS)?
+            .and_then(|txt| marketplace.parse_price(&txt));
+
+        // Getting the savings, falling back to comparing the list price with the current price
+        let savings_percentage = self
+            .find_first_text(document, SAVINGS_SELECTOR_STRS)?
+            .and_then(|txt| parse_percentage(&txt))
+            .or_else(|| current_price?.savings_percentage(list_price.as_ref()?))
+            .unwrap_or_default();
+
+        // Getting the deal badge
+        let deal_badge = self
+            .find_first_text(document, DEAL_BADGE_SELECTOR_STRS)?
+            .unwrap_or_default();
+
+        // Getting the coupon, which is either a percentage or a fixed amount
+        let coupon = self.find_first_text(document, COUPON_SELECTOR_STRS)?;
+        let coupon_percentage = coupon.as_deref().and_then(parse_percentage);
+        let coupon_amount = match coupon_percentage {
+            Some(_) => None,
+            None => coupon
+                .as_deref()
+                .and_then(|txt| marketplace.parse_price(txt)),
+        };
+
+        Ok(Pricing {
+            list_price: list_price.map(Into::into),
+            savings_percentage,
+            deal_badge,
+            coupon_amount: coupon_amount.map(Into::into),
+            coupon_percentage: coupon_percentage.unwrap_or_default(),
+        })
+    }
+
+    fn get_product_availability(
+        &self,
+        document: &Html,
+        marketplace: &Marketplace,
+    ) -> Result<Availability, ScraperError> {
+        const AVAILABILITY_SELECTOR_STR: &str = "#availability";
+        const ADD_TO_CART_SELECTOR_STR: &str = "#add-to-cart-button";
+        const MERCHANT_INFO_SELECTOR_STR: &str = "#merchant-info"; // Older layout, e.g. "Ships from and sold by Amazon.com."
+        const SOLD_BY_SELECTOR_STRS: &[&str] = &[
+            "#merchantInfoFeature_feature_div .offer-display-feature-text-message",
+            "#tabular-buybox .tabular-buybox-text[tabular-attribute-name=\"Sold by\"]",
+            "#sellerProfileTriggerId",
+        ];
+        const SHIPS_FROM_SELECTOR_STRS: &[&str] = &[
+            "#fulfillerInfoFeature_feature_div .offer-display-feature-text-message",
+            "#tabular-buybox .tabular-buybox-text[tabular-attribute-name=\"Ships from\"]",
+        ];
+
+        // Getting the availability text
+        let availability_text = self
+            .find_first_text(document, &[AVAILABILITY_SELECTOR_STR])?
+            .unwrap_or_default();
+
+        // Deciding whether the product can be bought
+        // Pages without an availability message are treated as in stock if they can be added to cart.
+        let in_stock = if availability_text.is_empty() {
+            self.has_element(document, ADD_TO_CART_SELECTOR_STR)?
         } else {
-            Err(CssError::new("Invalid Node found.").into())
-        }
+            is_in_stock(&availability_text, marketplace)
+        };
+
+        // Getting the seller and fulfiller
+        let merchant_info = self
+            .find_first_text(document, &[MERCHANT_INFO_SELECTOR_STR])?
+            .unwrap_or_default();
+        let (merchant_sold_by, merchant_ships_from) = parse_merchant_info(&merchant_info);
+        let sold_by = self
+            .find_first_text(document, SOLD_BY_SELECTOR_STRS)?
+            .or(merchant_sold_by)
+            .unwrap_or_default();
+        let ships_from = self
+            .find_first_text(document, SHIPS_FROM_SELECTOR_STRS)?
+            .or(merchant_ships_from)
+            .unwrap_or_default();
+
+        // Offers shipped by Amazon are fulfilled by Amazon, whoever the seller is
+        let fulfilled_by_amazon = ships_from.to_lowercase().contains("amazon")
+            || merchant_info.to_lowercase().contains("fulfilled by amazon");
+
+        Ok(Availability {
+            availability_text,
+            in_stock,
+            sold_by,
+            ships_from,
+            fulfilled_by_amazon,
+        })
+    }
+
+    fn has_element(&self, document: &Html, selector_str: &str) -> Result<bool, ScraperError> {
+        let selector = scraper::Selector::parse(selector_str).map_err(|e| ScraperError::Parse {
+            context: self.error_context(),
+            selector: selector_str.to_string(),
+            source: e.into(),
+        })?;
+        Ok(document.select(&selector).next().is_some())
     }
 }
 
+/// Fallback for page layouts without a recognised buy box.
+const GENERIC_PRICE_SELECTOR_STR: &str = ".a-offscreen";
+
+/// Reads the percentage out of texts such as "-25%", "(25%)" or "Save 10% with coupon".
+fn parse_percentage(txt: &str) -> Option<u32> {
+    let (before, _) = txt.split_once('%')?;
+    let digits = before
+        .trim_end()
+        .rsplit(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .next()?;
+    digits
+        .parse::<f32>()
+        .ok()
+        .map(|percentage| percentage.round() as u32)
+}
+
+/// Recognises the pages Amazon serves instead of a product page when it suspects a bot.
+/// Returns a description of the block page, if the document is one.
+fn detect_block_page(document: &Html) -> Option<&'static str> {
+    const CAPTCHA_FORM_SELECTOR_STR: &str = "form[action*=\"validateCaptcha\"]";
+    const BLOCK_PAGE_TEXTS: &[(&str, &str)] = &[
+        ("Robot Check", "Robot check page."),
+        ("Enter the characters you see below", "CAPTCHA page."),
+        (
+            "To discuss automated access to Amazon data",
+            "Automated access notice.",
+        ),
+        (
+            "Sorry, something went wrong",
+            "\"Sorry, something went wrong\" page.",
+        ),
+        (
+            "Sorry! Something went wrong!",
+            "\"Sorry, something went wrong\" page.",
+        ),
+    ];
+
+    let captcha_selector =
+        scraper::Selector::parse(CAPTCHA_FORM_SELECTOR_STR).expect("Valid selector.");
+    if document.select(&captcha_selector).next().is_some() {
+        return Some("CAPTCHA form.");
+    }
+
+    // Product pages always have a title, so only pages without one are searched for block texts
+    let title_selector = scraper::Selector::parse("#productTitle").expect("Valid selector.");
+    if document.select(&title_selector).next().is_some() {
+        return None;
+    }
+    let text = document.root_element().text().collect::<String>();
+    BLOCK_PAGE_TEXTS
+        .iter()
+        .find(|(marker, _)| text.contains(marker))
+        .map(|(_, reason)| *reason)
+}
+
+/// Availability message of products only offered by third parties outside the buy box.
+const THIRD_PARTY_ONLY_MARKER: &str = "available from these sellers";
+
+/// Each marketplace adds the out of stock messages of its own languages to the English ones.
+fn is_in_stock(availability_text: &str, marketplace: &Marketplace) -> bool {
+    let availability_text = availability_text.to_lowercase();
+    !OUT_OF_STOCK_MARKERS
+        .iter()
+        .chain(marketplace.out_of_stock_markers)
+        .chain([&THIRD_PARTY_ONLY_MARKER])
+        .any(|marker| availability_text.contains(marker))
+}
+
+/// Splits the older "merchant info" line into the seller and the shipper, if recognised.
+fn parse_merchant_info(merchant_info: &str) -> (Option<String>, Option<String>) {
+    const SHIPS_FROM_AND_SOLD_BY: &str = "Ships from and sold by ";
+    const SOLD_BY: &str = "Sold by ";
+    const FULFILLED_BY: &str = " and Fulfilled by ";
+
+    let trim = |name: &str| name.trim().trim_end_matches('.').to_string();
+
+    if let Some(merchant) = merchant_info.strip_prefix(SHIPS_FROM_AND_SOLD_BY) {
+        let merchant = trim(merchant);
+        return (Some(merchant.clone()), Some(merchant));
+    }
+
+    if let Some(rest) = merchant_info.strip_prefix(SOLD_BY) {
+        return match rest.split_once(FULFILLED_BY) {
+            Some((seller, fulfiller)) => (Some(trim(seller)), Some(trim(fulfiller))),
+            None => (Some(trim(rest)), None),
+        };
+    }
+
+    (None, None)
+}
+
 impl scraping_traits::Source for Amzn {
     fn get_source_name(&self) -> String {
         "amzn".to_string()
@@ -100,39 +431,336 @@ impl scraping_traits::Source for Amzn {
 }
 
 /// Using the default implementation for the BaseTraits
-impl BaseTraits for Amzn {}
+impl BaseTraits for Amzn {
+    fn error_context(&self) -> ErrorContext {
+        ErrorContext::product(self.get_source_name(), self.get_product_asin_code())
+    }
+
+    /// Amazon answers automated traffic with a 503 "dog page", which retrying only prolongs.
+    fn should_retry_status(&self, status: StatusCode) -> bool {
+        status != StatusCode::SERVICE_UNAVAILABLE
+    }
+}
 
 #[async_trait]
 impl scraping_traits::Scraper for Amzn {
     fn get_unique_id(&self) -> String {
         let product_id = self.get_product_asin_code();
         let source = self.get_source_name();
-        let unique_id = format!("{} - {}", source, product_id);
+        let marketplace = self
+            .get_marketplace()
+            .map_or(self.marketplace.as_str(), |m| m.code);
+        let unique_id = format!("{} - {} - {}", source, marketplace, product_id);
         unique_id
     }
 
+    fn get_identifier(&self) -> String {
+        self.get_product_asin_code()
+    }
+
+    fn get_target_domain(&self) -> Option<String> {
+        self.get_marketplace()
+            .ok()
+            .map(|marketplace| marketplace.domain.to_string())
+    }
+
+    request_accessors!();
+
     async fn scrape(
         &self,
         client: &Client,
-    ) -> Result<scraping::results::ScrapingResult, Box<dyn Error + Send>> {
+    ) -> Result<scraping::results::ScrapingResult, ScraperError> {
+        // Resolving the marketplace
+        let marketplace = self.get_marketplace()?;
+
         // Constructing the request
-        let request = self
-            .construct_request(client)
-            .map_err(|e| Box::new(e) as BoxedErr)?; // 'As' used to coerce concrete type into trait object.
+        let request = self.construct_request(client, marketplace)?;
+        let context = self.error_context().with_url(request.url().as_str());
 
         // Performing the request
-        let raw_html_string = self
-            .request(client, request, None, None)
-            .await
-            .map_err(|e| Box::new(e) as BoxedErr)?
+        let response = match self.request(client, request, None, None).await {
+            Err(ScraperError::HttpStatus {
+                context,
+                status: 503,
+                ..
+            }) => {
+                return Err(ScraperError::Blocked {
+                    context,
+                    reason: "Service unavailable (503) page.".to_string(),
+                })
+            }
+            response => response?,
+        };
+        let raw_html_string = response
             .text()
             .await
-            .map_err(|e| Box::new(e) as BoxedErr)?;
+            .map_err(|e| ScraperError::from_request(context.clone(), e))?;
 
         // Parsing the response into a HTML Document
         let document = Html::parse_document(&raw_html_string);
 
+        // Checking that Amazon served the product page rather than a block page
+        if let Some(reason) = detect_block_page(&document) {
+            return Err(ScraperError::Blocked {
+                context,
+                reason: reason.to_string(),
+            });
+        }
+
         // Extracting the relevant information from the HTML Document
-        self.get_product_information(&document)
+        self.get_product_information(&document, marketplace)
+    }
+}
+
+/// Registers the Amzn source under its source name.
+pub(crate) fn register(registry: &mut SourceRegistry) {
+    registry.register("amzn", |source| match source {
+        RequestSource::Amzn(mut request) => {
+            fill_request_id(&mut request.request_id);
+            Some(Box::new(request))
+        }
+        _ => None,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use scraper::Html;
+
+    use super::{
+        detect_block_page, is_in_stock, marketplace::Marketplace, parse_merchant_info,
+        parse_percentage,
+    };
+    use crate::scraping::{
+        requests::Amzn,
+        results::{Money, ScrapingResult},
+    };
+
+    /// Extracts the product from a recorded page of the marketplace.
+    fn scrape(html: &str, marketplace: &str) -> ScrapingResult {
+        let request = Amzn {
+            product_code: "B000TEST01".to_string(),
+            marketplace: marketplace.to_string(),
+            ..Default::default()
+        };
+        request
+            .get_product_information(
+                &Html::parse_document(html),
+                Marketplace::find(marketplace).unwrap(),
+            )
+            .unwrap()
+    }
+
+    fn money(amount_minor: i64, currency: &str) -> Option<Money> {
+        Some(Money {
+            amount_minor,
+            currency: currency.to_string(),
+        })
+    }
+
+    #[test]
+    fn reads_products_sold_by_amazon() {
+        let result = scrape(include_str!("../../fixtures/amzn/in-stock.html"), "sg");
+
+        assert_eq!(result.name, "Acme Noise Cancelling Headphones, Black");
+        assert_eq!(result.money, money(129_900, "SGD")); // Not the carousel price
+        let availability = result.availability.unwrap();
+        assert_eq!(availability.availability_text, "In stock");
+        assert!(availability.in_stock);
+        assert_eq!(availability.sold_by, "Amazon.sg");
+        assert_eq!(availability.ships_from, "Amazon.sg");
+        assert!(availability.fulfilled_by_amazon);
+    }
+
+    #[test]
+    fn leaves_out_prices_of_products_only_sold_by_third_parties() {
+        let result = scrape(
+            include_str!("../../fixtures/amzn/third-party-only.html"),
+            "com",
+        );
+
+        assert_eq!(result.money, None); // The carousel price is not the product's
+        assert_eq!(result.price, 0.0);
+        let availability = result.availability.unwrap();
+        assert!(!availability.in_stock);
+        assert_eq!(availability.sold_by, "Hose Depot");
+        assert_eq!(availability.ships_from, "Amazon");
+        assert!(availability.fulfilled_by_amazon);
+    }
+
+    #[test]
+    fn falls_back_to_the_add_to_cart_button_and_any_price() {
+        let html = r#"<span id="productTitle">Widget</span>
+            <div class="some-new-layout"><span class="a-offscreen">$12.99</span></div>
+            <input id="add-to-cart-button" type="submit">"#;
+        let result = scrape(html, "com");
+
+        assert!(result.availability.unwrap().in_stock);
+        assert_eq!(result.money, money(1_299, "USD"));
+    }
+
+    #[test]
+    fn recognises_out_of_stock_messages() {
+        let com = Marketplace::find("com").unwrap();
+        assert!(is_in_stock("In Stock.", com));
+        assert!(is_in_stock("Only 3 left in stock - order soon.", com));
+        assert!(!is_in_stock("Currently unavailable.", com));
+        assert!(!is_in_stock("Temporarily out of stock.", com));
+        assert!(!is_in_stock("Available from these sellers.", com));
+    }
+
+    #[test]
+    fn reads_unavailable_products_in_german() {
+        let result = scrape(
+            include_str!("../../fixtures/amzn/de-unavailable.html"),
+            "de",
+        );
+
+        assert!(!result.availability.unwrap().in_stock);
+        assert_eq!(result.money, None); // The carousel price is not the product's
+        assert_eq!(result.currency, "EUR");
+        assert_eq!(result.metadata["marketplace"], "de");
+    }
+
+    #[test]
+    fn reads_prices_in_yen() {
+        let result = scrape(
+            include_str!("../../fixtures/amzn/co-jp-in-stock.html"),
+            "co.jp",
+        );
+
+        assert!(result.availability.unwrap().in_stock);
+        assert_eq!(result.money, money(12_800, "JPY"));
+        assert_eq!(result.price, 12_800.0);
+    }
+
+    #[test]
+    fn recognises_out_of_stock_messages_of_each_marketplace() {
+        let cases = [
+            ("de", "Derzeit nicht verfügbar.", false),
+            ("de", "Nur noch 3 auf Lager", true),
+            ("fr", "Actuellement indisponible.", false),
+            ("fr", "En stock", true),
+            ("ca", "Actuellement indisponible.", false),
+            ("it", "Non disponibile.", false),
+            ("it", "Disponibilità immediata", true),
+            ("es", "No disponible.", false),
+            ("es", "En stock", true),
+            ("com.mx", "Agotado", false),
+            ("nl", "Momenteel niet verkrijgbaar.", false),
+            ("se", "För närvarande inte tillgänglig.", false),
+            ("pl", "Obecnie niedostępny.", false),
+            ("pl", "Dostępny", true),
+            ("com.br", "Não disponível.", false),
+            ("com.br", "Em estoque", true),
+            ("co.jp", "現在在庫切れです。", false),
+            ("co.jp", "在庫あり。", true),
+            ("de", "Currently unavailable.", false), // English is recognised everywhere
+        ];
+        for (code, availability_text, in_stock) in cases {
+            let marketplace = Marketplace::find(code).unwrap();
+            assert_eq!(
+                is_in_stock(availability_text, marketplace),
+                in_stock,
+                "{} on {}",
+                availability_text,
+                code
+            );
+        }
+    }
+
+    #[test]
+    fn reads_deals_and_coupons() {
+        let result = scrape(include_str!("../../fixtures/amzn/deal-coupon.html"), "com");
+
+        assert_eq!(result.money, money(7_499, "USD")); // Not the list price
+        let pricing = result.pricing.unwrap();
+        assert_eq!(pricing.list_price, money(9_999, "USD"));
+        assert_eq!(pricing.savings_percentage, 25);
+        assert_eq!(pricing.deal_badge, "Limited time deal");
+        assert_eq!(pricing.coupon_amount, money(500, "USD"));
+        assert_eq!(pricing.coupon_percentage, 0);
+
+        let availability = result.availability.unwrap();
+        assert_eq!(availability.sold_by, "Acme Official Store");
+        assert!(availability.fulfilled_by_amazon);
+    }
+
+    #[test]
+    fn computes_savings_missing_from_the_page() {
+        let html = r#"<span id="productTitle">Widget</span>
+            <div id="availability">In Stock</div>
+            <div id="corePrice_feature_div">
+                <span class="priceToPay"><span class="a-offscreen">$15.00</span></span>
+                <span class="a-text-price" data-a-strike="true"><span class="a-offscreen">$20.00</span></span>
+            </div>
+            <div id="vpcButton"><span class="a-color-success">Save 10% with coupon</span></div>"#;
+        let pricing = scrape(html, "com").pricing.unwrap();
+
+        assert_eq!(pricing.list_price, money(2_000, "USD"));
+        assert_eq!(pricing.savings_percentage, 25);
+        assert_eq!(pricing.coupon_percentage, 10);
+        assert_eq!(pricing.coupon_amount, None);
+    }
+
+    #[test]
+    fn parses_percentages() {
+        assert_eq!(parse_percentage("-25%"), Some(25));
+        assert_eq!(parse_percentage("(33%)"), Some(33));
+        assert_eq!(parse_percentage("Save 12.5% with coupon"), Some(13));
+        assert_eq!(parse_percentage("Apply $5.00 coupon"), None);
+        assert_eq!(parse_percentage("%"), None);
+    }
+
+    #[test]
+    fn detects_block_pages() {
+        let page = |html: &str| detect_block_page(&Html::parse_document(html));
+
+        assert_eq!(
+            page(include_str!("../../fixtures/amzn/captcha.html")),
+            Some("CAPTCHA form.")
+        );
+        assert_eq!(
+            page("<title>Robot Check</title><p>Type the characters</p>"),
+            Some("Robot check page.")
+        );
+        assert_eq!(
+            page("<p>To discuss automated access to Amazon data please contact us.</p>"),
+            Some("Automated access notice.")
+        );
+        assert_eq!(
+            page("<h1>Sorry! Something went wrong!</h1>"),
+            Some("\"Sorry, something went wrong\" page.")
+        );
+    }
+
+    #[test]
+    fn does_not_mistake_product_pages_for_block_pages() {
+        for html in [
+            include_str!("../../fixtures/amzn/in-stock.html"),
+            include_str!("../../fixtures/amzn/deal-coupon.html"),
+            // Product pages may quote block texts, e.g. in reviews
+            r#"<span id="productTitle">Robot Check Toy</span><p>Robot Check</p>"#,
+        ] {
+            assert_eq!(detect_block_page(&Html::parse_document(html)), None);
+        }
+    }
+
+    #[test]
+    fn splits_merchant_info() {
+        let owned = |name: &str| Some(name.to_string());
+        assert_eq!(
+            parse_merchant_info("Ships from and sold by Amazon.com."),
+            (owned("Amazon.com"), owned("Amazon.com"))
+        );
+        assert_eq!(
+            parse_merchant_info("Sold by Hose Depot and Fulfilled by Amazon."),
+            (owned("Hose Depot"), owned("Amazon"))
+        );
+        assert_eq!(
+            parse_merchant_info("Sold by Hose Depot."),
+            (owned("Hose Depot"), None)
+        );
+        assert_eq!(parse_merchant_info(""), (None, None));
     }
 }
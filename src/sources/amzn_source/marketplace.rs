@@ -0,0 +1,227 @@
+use crate::price::Money;
+
+/// An Amazon storefront, along with how it formats prices.
+pub(crate) struct Marketplace {
+    pub(crate) code: &'static str,
+    pub(crate) domain: &'static str,
+    pub(crate) currency: &'static str, // ISO 4217 currency code
+    decimal_separator: Option<char>,   // `None` for currencies without minor units, e.g. JPY.
+    /// Lower case availability messages in the marketplace's languages that mean the product
+    /// cannot currently be bought. English messages are recognised on every marketplace.
+    pub(crate) out_of_stock_markers: &'static [&'static str],
+}
+
+/// Marketplace used by requests that do not specify one.
+pub(crate) const DEFAULT_MARKETPLACE: &str = "sg";
+
+const GERMAN_OUT_OF_STOCK_MARKERS: &[&str] = &[
+    "derzeit nicht verfügbar",
+    "nicht auf lager",
+    "nicht vorrätig",
+    "erhältlich bei diesen anbietern",
+];
+const FRENCH_OUT_OF_STOCK_MARKERS: &[&str] = &[
+    "actuellement indisponible",
+    "en rupture de stock",
+    "disponible auprès de ces vendeurs",
+];
+const ITALIAN_OUT_OF_STOCK_MARKERS: &[&str] = &[
+    "non disponibile",
+    "esaurito",
+    "disponibile presso questi venditori",
+];
+const SPANISH_OUT_OF_STOCK_MARKERS: &[&str] = &[
+    "no disponible",
+    "agotado",
+    "disponible a través de estos vendedores",
+];
+const DUTCH_OUT_OF_STOCK_MARKERS: &[&str] =
+    &["niet verkrijgbaar", "niet beschikbaar", "niet op voorraad"];
+const SWEDISH_OUT_OF_STOCK_MARKERS: &[&str] = &["inte tillgänglig", "inte i lager", "slut i lager"];
+const POLISH_OUT_OF_STOCK_MARKERS: &[&str] = &["niedostępny", "brak w magazynie"];
+const PORTUGUESE_OUT_OF_STOCK_MARKERS: &[&str] = &[
+    "não disponível",
+    "indisponível",
+    "fora de estoque",
+    "esgotado",
+];
+const JAPANESE_OUT_OF_STOCK_MARKERS: &[&str] = &[
+    "在庫切れ",
+    "お取り扱いできません",
+    "こちらからお買い求めいただけます", // Only offered by third parties outside the buy box.
+];
+const ARABIC_OUT_OF_STOCK_MARKERS: &[&str] = &["غير متوفر", "غير متاح"];
+
+const MARKETPLACES: &[Marketplace] = &[
+    Marketplace::new("sg", "www.amazon.sg", "SGD", Some('.'), &[]),
+    Marketplace::new("com", "www.amazon.com", "USD", Some('.'), &[]),
+    Marketplace::new(
+        "ca",
+        "www.amazon.ca",
+        "CAD",
+        Some('.'),
+        FRENCH_OUT_OF_STOCK_MARKERS,
+    ),
+    Marketplace::new(
+        "com.mx",
+        "www.amazon.com.mx",
+        "MXN",
+        Some('.'),
+        SPANISH_OUT_OF_STOCK_MARKERS,
+    ),
+    Marketplace::new(
+        "com.br",
+        "www.amazon.com.br",
+        "BRL",
+        Some(','),
+        PORTUGUESE_OUT_OF_STOCK_MARKERS,
+    ),
+    Marketplace::new("co.uk", "www.amazon.co.uk", "GBP", Some('.'), &[]),
+    Marketplace::new(
+        "de",
+        "www.amazon.de",
+        "EUR",
+        Some(','),
+        GERMAN_OUT_OF_STOCK_MARKERS,
+    ),
+    Marketplace::new(
+        "fr",
+        "www.amazon.fr",
+        "EUR",
+        Some(','),
+        FRENCH_OUT_OF_STOCK_MARKERS,
+    ),
+    Marketplace::new(
+        "it",
+        "www.amazon.it",
+        "EUR",
+        Some(','),
+        ITALIAN_OUT_OF_STOCK_MARKERS,
+    ),
+    Marketplace::new(
+        "es",
+        "www.amazon.es",
+        "EUR",
+        Some(','),
+        SPANISH_OUT_OF_STOCK_MARKERS,
+    ),
+    Marketplace::new(
+        "nl",
+        "www.amazon.nl",
+        "EUR",
+        Some(','),
+        DUTCH_OUT_OF_STOCK_MARKERS,
+    ),
+    Marketplace::new(
+        "se",
+        "www.amazon.se",
+        "SEK",
+        Some(','),
+        SWEDISH_OUT_OF_STOCK_MARKERS,
+    ),
+    Marketplace::new(
+        "pl",
+        "www.amazon.pl",
+        "PLN",
+        Some(','),
+        POLISH_OUT_OF_STOCK_MARKERS,
+    ),
+    Marketplace::new(
+        "ae",
+        "www.amazon.ae",
+        "AED",
+        Some('.'),
+        ARABIC_OUT_OF_STOCK_MARKERS,
+    ),
+    Marketplace::new("in", "www.amazon.in", "INR", Some('.'), &[]),
+    Marketplace::new(
+        "co.jp",
+        "www.amazon.co.jp",
+        "JPY",
+        None,
+        JAPANESE_OUT_OF_STOCK_MARKERS,
+    ),
+    Marketplace::new("com.au", "www.amazon.com.au", "AUD", Some('.'), &[]),
+];
+
+impl Marketplace {
+    const fn new(
+        code: &'static str,
+        domain: &'static str,
+        currency: &'static str,
+        decimal_separator: Option<char>,
+        out_of_stock_markers: &'static [&'static str],
+    ) -> Self {
+        Marketplace {
+            code,
+            domain,
+            currency,
+            decimal_separator,
+            out_of_stock_markers,
+        }
+    }
+
+    /// Looks up a marketplace by its domain suffix (e.g. "co.uk"). Empty codes use the default marketplace.
+    pub(crate) fn find(code: &str) -> Option<&'static Marketplace> {
+        let code = match code.trim().trim_start_matches('.') {
+            "" => DEFAULT_MARKETPLACE,
+            code => code,
+        };
+        MARKETPLACES
+            .iter()
+            .find(|marketplace| marketplace.code.eq_ignore_ascii_case(code))
+    }
+
+    pub(crate) fn product_url(&self, product_code: &str) -> String {
+        format!("https://{}/dp/{}", self.domain, product_code)
+    }
+
+    /// Parses a price as displayed on this marketplace, e.g. "S$1,299.00", "1.299,00 €" or "￥12,800".
+    pub(crate) fn parse_price(&self, raw: &str) -> Option<Money> {
+        Money::parse(raw, self.currency, self.decimal_separator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Marketplace;
+
+    #[test]
+    fn finds_marketplaces_by_domain_suffix() {
+        assert_eq!(Marketplace::find("").unwrap().code, "sg");
+        assert_eq!(Marketplace::find(".co.uk").unwrap().code, "co.uk");
+        assert_eq!(Marketplace::find(" DE ").unwrap().domain, "www.amazon.de");
+        assert!(Marketplace::find("co.zz").is_none());
+    }
+
+    #[test]
+    fn parses_prices_in_the_marketplace_format() {
+        let amount = |code: &str, raw: &str| {
+            Marketplace::find(code)
+                .unwrap()
+                .parse_price(raw)
+                .map(|money| (money.amount_minor, money.currency))
+        };
+        assert_eq!(
+            amount("sg", "S$1,299.00"),
+            Some((129_900, "SGD".to_string()))
+        );
+        assert_eq!(
+            amount("de", "1.299,00 €"),
+            Some((129_900, "EUR".to_string()))
+        );
+        assert_eq!(
+            amount("se", "1 299,00 kr"),
+            Some((129_900, "SEK".to_string()))
+        );
+        assert_eq!(
+            amount("com.br", "R$ 49,90"),
+            Some((4_990, "BRL".to_string()))
+        );
+        assert_eq!(
+            amount("co.jp", "￥12,800"),
+            Some((12_800, "JPY".to_string()))
+        );
+        assert_eq!(amount("com", "Currently unavailable"), None);
+    }
+}
@@ -0,0 +1,406 @@
+use async_trait::async_trait;
+use regex::Regex;
+use reqwest::{Client, Url};
+use scraper::Html;
+
+use crate::{
+    errors::{CssError, ErrorContext, ScraperError},
+    price::Money,
+    scraping::{
+        self,
+        requests::{scraping_request::Source as RequestSource, FieldSelector, Generic},
+        results::{Availability, ScrapingResult, ScrapingStatus},
+    },
+    scraping_traits::{self, BaseTraits, Scraper, Source},
+    sources::{fill_request_id, SourceRegistry, OUT_OF_STOCK_MARKERS},
+};
+
+impl Generic {
+    fn get_url(&self) -> Result<Url, ScraperError> {
+        Url::parse(&self.url).map_err(|e| ScraperError::InvalidRequest {
+            context: self.error_context(),
+            reason: format!("Invalid url {:?}: {}", self.url, e),
+        })
+    }
+
+    /// Returns the selector configured for the field, if any.
+    fn get_selector<'a>(&self, field: &'a Option<FieldSelector>) -> Option<&'a FieldSelector> {
+        field.as_ref().filter(|selector| !selector.css.is_empty())
+    }
+
+    /// Extracts a field from the document: the attribute or text of the first matching element,
+    /// narrowed down by the selector's regex if it has one.
+    fn extract(
+        &self,
+        document: &Html,
+        field: &Option<FieldSelector>,
+    ) -> Result<Option<String>, ScraperError> {
+        let selector = match self.get_selector(field) {
+            Some(selector) => selector,
+            None => return Ok(None),
+        };
+        let parse_error = |source| ScraperError::Parse {
+            context: self.error_context(),
+            selector: selector.css.clone(),
+            source,
+        };
+
+        // Reading the raw value
+        let value = match selector.attribute.as_str() {
+            "" => self.find_element_text(document, &selector.css),
+            attribute => self.find_css_attribute(document, &selector.css, attribute),
+        }
+        .map_err(parse_error)?;
+
+        // Post-processing the value
+        let value = match (value, selector.regex.as_str()) {
+            (Some(value), "") => Some(value),
+            (Some(value), pattern) => {
+                let regex = Regex::new(pattern).map_err(|e| ScraperError::InvalidRequest {
+                    context: self.error_context(),
+                    reason: format!("Invalid regex {:?}: {}", pattern, e),
+                })?;
+                regex.captures(&value).and_then(|captures| {
+                    captures
+                        .get(1)
+                        .or_else(|| captures.get(0))
+                        .map(|capture| capture.as_str().trim().to_string())
+                })
+            }
+            (None, _) => None,
+        };
+
+        Ok(value.filter(|value| !value.is_empty()))
+    }
+
+    fn get_product_information(
+        &self,
+        document: &Html,
+        url: &Url,
+    ) -> Result<ScrapingResult, ScraperError> {
+        let missing = |field: &Option<FieldSelector>| ScraperError::Parse {
+            context: self.error_context(),
+            selector: field
+                .as_ref()
+                .map(|selector| selector.css.clone())
+                .unwrap_or_default(),
+            source: CssError::new("Failed to find Css Node."),
+        };
+
+        // Getting the name
+        let name = self
+            .extract(document, &self.name)?
+            .ok_or_else(|| missing(&self.name))?;
+
+        // Getting the availability, if configured
+        let availability = self
+            .get_selector(&self.availability)
+            .map(|_| -> Result<Availability, ScraperError> {
+                let availability_text = self
+                    .extract(document, &self.availability)?
+                    .unwrap_or_default();
+                let lowercase_text = availability_text.to_lowercase();
+                let in_stock = !OUT_OF_STOCK_MARKERS
+                    .iter()
+                    .any(|marker| lowercase_text.contains(marker));
+                Ok(Availability {
+                    availability_text,
+                    in_stock,
+                    ..Default::default()
+                })
+            })
+            .transpose()?;
+        let in_stock = availability.as_ref().is_none_or(|a| a.in_stock);
+
+        // Getting the price
+        // Out of stock products often have no price on the page.
+        let raw_price = self.extract(document, &self.price)?;
+        if raw_price.is_none() && in_stock {
+            return Err(missing(&self.price));
+        }
+
+        // Getting the currency, from the page, then the request, then the price itself
+        let currency = self
+            .extract(document, &self.currency)?
+            .and_then(|currency| match currency.len() {
+                3 if currency.chars().all(|c| c.is_ascii_alphabetic()) => {
+                    Some(currency.to_uppercase())
+                }
+                _ => Money::detect_currency(&currency),
+            })
+            .or_else(|| Some(self.default_currency.to_uppercase()).filter(|c| !c.is_empty()))
+            .or_else(|| Money::detect_currency(raw_price.as_deref()?));
+
+        let money = match (raw_price, currency.as_deref()) {
+            (Some(raw_price), Some(currency)) => {
+                Some(Money::parse(&raw_price, currency, None).ok_or_else(|| {
+                    ScraperError::PriceFormat {
+                        context: self.error_context(),
+                        raw: raw_price.clone(),
+                    }
+                })?)
+            }
+            (Some(_), None) => {
+                return Err(ScraperError::InvalidRequest {
+                    context: self.error_context(),
+                    reason: "No currency found on the page, and no default_currency given."
+                        .to_string(),
+                })
+            }
+            (None, _) => None,
+        };
+
+        // Getting the image, resolving relative links against the page url
+        let image_url = self
+            .extract(document, &self.image)?
+            .map(|image| {
+                url.join(&image)
+                    .map_or(image, |image_url| image_url.to_string())
+            })
+            .unwrap_or_default();
+
+        // Constructing the ScrapingResult
+        Ok(ScrapingResult {
+            source: self.get_source_name(),
+            utc_timestamp: self.get_current_utc_time(),
+            name,
+            identifier: self.get_identifier(),
+            price: money.as_ref().map_or(0.0, Money::as_f32),
+            status: ScrapingStatus::Success as i32,
+            failure: None,
+            request_id: self.get_request_id(),
+            availability,
+            currency: currency.unwrap_or_default(),
+            money: money.map(Into::into),
+            pricing: None,
+            image_url,
+            attributes: self.get_attributes(),
+            metadata: self.get_result_metadata(),
+        })
+    }
+}
+
+impl scraping_traits::Source for Generic {
+    fn get_source_name(&self) -> String {
+        "generic".to_string()
+    }
+}
+
+impl BaseTraits for Generic {
+    fn error_context(&self) -> ErrorContext {
+        ErrorContext::product(self.get_source_name(), self.get_identifier())
+    }
+}
+
+#[async_trait]
+impl scraping_traits::Scraper for Generic {
+    fn get_unique_id(&self) -> String {
+        format!("{} - {}", self.get_source_name(), self.get_identifier())
+    }
+
+    fn get_identifier(&self) -> String {
+        match self.identifier.is_empty() {
+            true => self.url.to_owned(),
+            false => self.identifier.to_owned(),
+        }
+    }
+
+    fn get_target_domain(&self) -> Option<String> {
+        Url::parse(&self.url).ok()?.host_str().map(str::to_string)
+    }
+
+    request_accessors!();
+
+    async fn scrape(
+        &self,
+        client: &Client,
+    ) -> Result<scraping::results::ScrapingResult, ScraperError> {
+        // Constructing the request
+        let url = self.get_url()?;
+        let context = self.error_context().with_url(url.as_str());
+        let request = client
+            .get(url.clone())
+            .build()
+            .map_err(|e| ScraperError::from_request(context.clone(), e))?;
+
+        // Performing the request
+        let raw_html_string = self
+            .request(client, request, None, None)
+            .await?
+            .text()
+            .await
+            .map_err(|e| ScraperError::from_request(context, e))?;
+
+        // Parsing the response into a HTML Document
+        let document = Html::parse_document(&raw_html_string);
+
+        // Extracting the configured fields from the HTML Document
+        self.get_product_information(&document, &url)
+    }
+}
+
+/// Registers the Generic source under its source name.
+pub(crate) fn register(registry: &mut SourceRegistry) {
+    registry.register("generic", |source| match source {
+        RequestSource::Generic(mut request) => {
+            fill_request_id(&mut request.request_id);
+            Some(request) // Already boxed by prost, see build.rs.
+        }
+        _ => None,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Url;
+    use scraper::Html;
+
+    use crate::{
+        errors::ScraperError,
+        scraping::{
+            requests::{FieldSelector, Generic},
+            results::{Money, ScrapingResult, ScrapingStatus},
+        },
+    };
+
+    const PRODUCT_URL: &str = "https://shop.beanthere.example/products/duo";
+
+    fn field(css: &str, attribute: &str, regex: &str) -> Option<FieldSelector> {
+        Some(FieldSelector {
+            css: css.to_string(),
+            attribute: attribute.to_string(),
+            regex: regex.to_string(),
+        })
+    }
+
+    fn request() -> Generic {
+        Generic {
+            url: PRODUCT_URL.to_string(),
+            name: field(".product-title", "", ""),
+            price: field(".price", "", ""),
+            ..Default::default()
+        }
+    }
+
+    fn scrape(request: &Generic, html: &str) -> Result<ScrapingResult, ScraperError> {
+        let document = Html::parse_document(html);
+        request.get_product_information(&document, &Url::parse(PRODUCT_URL).unwrap())
+    }
+
+    fn money(amount_minor: i64, currency: &str) -> Option<Money> {
+        Some(Money {
+            amount_minor,
+            currency: currency.to_string(),
+        })
+    }
+
+    #[test]
+    fn extracts_attributes_and_regex_captures() {
+        let request = Generic {
+            price: field(".price-box", "", r"([\d.,]+)\s*€"),
+            currency: field("meta[itemprop=priceCurrency]", "content", ""),
+            availability: field("link.availability", "href", ""),
+            image: field("img.gallery-main", "src", ""),
+            ..request()
+        };
+        let result = scrape(
+            &request,
+            include_str!("../../fixtures/generic/product.html"),
+        )
+        .unwrap();
+
+        assert_eq!(result.name, "Duo Espresso Machine");
+        assert_eq!(result.identifier, PRODUCT_URL);
+        assert_eq!(result.money, money(129999, "EUR"));
+        assert_eq!(result.currency, "EUR");
+        let availability = result.availability.unwrap();
+        assert_eq!(availability.availability_text, "https://schema.org/InStock");
+        assert!(availability.in_stock);
+        assert_eq!(
+            result.image_url,
+            "https://shop.beanthere.example/media/duo.jpg"
+        );
+    }
+
+    #[test]
+    fn reads_the_currency_from_the_page_then_the_request_then_the_price() {
+        let html = include_str!("../../fixtures/generic/product.html");
+        let currency = |currency: Option<FieldSelector>, default_currency: &str| {
+            let request = Generic {
+                currency,
+                default_currency: default_currency.to_string(),
+                ..request()
+            };
+            scrape(&request, html).map(|result| result.currency)
+        };
+
+        // A currency code on the page wins over the request's default
+        let code = field("meta[itemprop=priceCurrency]", "content", "");
+        assert_eq!(currency(code, "chf").unwrap(), "EUR");
+        // So does a currency symbol
+        assert_eq!(currency(field(".currency", "", ""), "chf").unwrap(), "EUR");
+        // The request's default wins over the symbol within the price
+        assert_eq!(currency(None, "chf").unwrap(), "CHF");
+        assert_eq!(currency(field(".missing", "", ""), "chf").unwrap(), "CHF");
+        assert_eq!(currency(None, "").unwrap(), "EUR");
+
+        // Without a symbol within the price, a currency has to be given
+        let request = Generic {
+            price: field(".price", "", r"[\d.,]+"),
+            ..request()
+        };
+        assert!(matches!(
+            scrape(&request, html),
+            Err(ScraperError::InvalidRequest { .. })
+        ));
+    }
+
+    #[test]
+    fn reports_out_of_stock_products_without_a_price() {
+        let html = include_str!("../../fixtures/generic/out-of-stock.html");
+        for availability in [
+            field(".stock", "", ""),
+            field("link.availability", "href", ""),
+        ] {
+            let request = Generic {
+                availability,
+                image: field("img.gallery-main", "src", ""),
+                default_currency: "EUR".to_string(),
+                ..request()
+            };
+            let result = scrape(&request, html).unwrap();
+
+            assert_eq!(result.status, ScrapingStatus::Success as i32);
+            assert_eq!(result.name, "Duo Milk Frother");
+            assert!(!result.availability.unwrap().in_stock);
+            assert_eq!(result.money, None);
+            assert_eq!(result.price, 0.0);
+            assert_eq!(
+                result.image_url,
+                "https://cdn.beanthere.example/frother.jpg"
+            );
+        }
+    }
+
+    #[test]
+    fn requires_a_price_unless_out_of_stock() {
+        // Without an availability, the product is assumed to be in stock
+        let html = include_str!("../../fixtures/generic/out-of-stock.html");
+        assert!(matches!(
+            scrape(&request(), html),
+            Err(ScraperError::Parse { selector, .. }) if selector == ".price"
+        ));
+
+        // Likewise when the availability does not read as out of stock
+        let request = Generic {
+            price: field(".price", "", ""),
+            availability: field("link.availability", "href", ""),
+            ..request()
+        };
+        let html = include_str!("../../fixtures/generic/product.html").replace("1.299,99 €", "");
+        assert!(matches!(
+            scrape(&request, &html),
+            Err(ScraperError::Parse { .. })
+        ));
+    }
+}
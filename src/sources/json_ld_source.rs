@@ -0,0 +1,607 @@
+use async_trait::async_trait;
+use reqwest::{Client, Url};
+use scraper::{ElementRef, Html, Selector};
+use serde_json::Value;
+
+use crate::{
+    errors::{CssError, ErrorContext, ScraperError},
+    price::Money,
+    scraping::{
+        self,
+        requests::{scraping_request::Source as RequestSource, JsonLd},
+        results::{Availability, ScrapingResult, ScrapingStatus},
+    },
+    scraping_traits::{self, BaseTraits, Scraper, Source},
+    sources::{fill_request_id, SourceRegistry},
+};
+
+const JSON_LD_SELECTOR_STR: &str = "script[type=\"application/ld+json\"]";
+
+/// schema.org availability values under which the product can be bought.
+const IN_STOCK_AVAILABILITIES: &[&str] = &[
+    "instock",
+    "in stock",
+    "limitedavailability",
+    "onlineonly",
+    "instoreonly",
+];
+
+/// Product details found on a page. Every field is optional, so that the details found in
+/// JSON-LD can be completed with those found in microdata and OpenGraph tags.
+#[derive(Debug, Default)]
+struct ProductData {
+    name: Option<String>,
+    price: Option<String>, // As written on the page
+    /// `.` for machine-readable prices, `None` for visible text whose separator must be guessed.
+    price_decimal_separator: Option<char>,
+    currency: Option<String>,
+    availability: Option<String>,
+    sku: Option<String>,
+    gtin: Option<String>,
+    brand: Option<String>,
+    image: Option<String>,
+}
+
+impl ProductData {
+    /// Fills in the fields missing from `self` with those of `other`.
+    fn or(self, other: ProductData) -> ProductData {
+        // Keeping the price along with its decimal separator
+        let (price, price_decimal_separator) = match self.price {
+            Some(price) => (Some(price), self.price_decimal_separator),
+            None => (other.price, other.price_decimal_separator),
+        };
+        ProductData {
+            name: self.name.or(other.name),
+            price,
+            price_decimal_separator,
+            currency: self.currency.or(other.currency),
+            availability: self.availability.or(other.availability),
+            sku: self.sku.or(other.sku),
+            gtin: self.gtin.or(other.gtin),
+            brand: self.brand.or(other.brand),
+            image: self.image.or(other.image),
+        }
+    }
+
+    /// Reads a schema.org `Product` node, along with the first of its offers that has a price.
+    fn from_json_ld(product: &Value) -> ProductData {
+        let offer = find_offer(product.get("offers").unwrap_or(&Value::Null));
+        let offer_value = |key: &str| offer.and_then(|offer| json_string(offer.get(key)?));
+        let price_specification = offer.and_then(|offer| offer.get("priceSpecification"));
+
+        ProductData {
+            name: json_string(product.get("name").unwrap_or(&Value::Null)),
+            price: offer_value("price")
+                .or_else(|| offer_value("lowPrice")) // AggregateOffer
+                .or_else(|| json_string(price_specification?.get("price")?)),
+            price_decimal_separator: Some('.'),
+            currency: offer_value("priceCurrency")
+                .or_else(|| json_string(price_specification?.get("priceCurrency")?)),
+            availability: offer_value("availability"),
+            sku: json_string(product.get("sku").unwrap_or(&Value::Null)),
+            gtin: ["gtin", "gtin13", "gtin12", "gtin14", "gtin8"]
+                .iter()
+                .find_map(|key| json_string(product.get(key)?)),
+            brand: product
+                .get("brand")
+                .and_then(|brand| json_string(brand).or_else(|| json_string(brand.get("name")?))),
+            image: product.get("image").and_then(|image| match image {
+                Value::Array(images) => images.iter().find_map(json_string),
+                Value::Object(_) => json_string(image.get("url")?),
+                _ => json_string(image),
+            }),
+        }
+    }
+}
+
+/// Reads strings and numbers as strings, ignoring anything else.
+fn json_string(value: &Value) -> Option<String> {
+    let string = match value {
+        Value::String(string) => string.trim().to_string(),
+        Value::Number(number) => number.to_string(),
+        _ => return None,
+    };
+    Some(string).filter(|string| !string.is_empty())
+}
+
+fn has_type(node: &Value, type_name: &str) -> bool {
+    match node.get("@type") {
+        Some(Value::String(node_type)) => node_type == type_name,
+        Some(Value::Array(node_types)) => node_types.iter().any(|t| t == type_name),
+        _ => false,
+    }
+}
+
+/// Finds the first `Product` node, searching through arrays, `@graph` and nested nodes.
+fn find_product(node: &Value) -> Option<&Value> {
+    match node {
+        Value::Object(_) if has_type(node, "Product") => Some(node),
+        Value::Object(object) => object.values().find_map(find_product),
+        Value::Array(nodes) => nodes.iter().find_map(find_product),
+        _ => None,
+    }
+}
+
+/// Returns the first offer with a price, looking inside `AggregateOffer`s if needed.
+fn find_offer(offers: &Value) -> Option<&Value> {
+    let has_price = |offer: &Value| {
+        ["price", "lowPrice", "priceSpecification"]
+            .iter()
+            .any(|key| offer.get(key).is_some())
+    };
+    match offers {
+        Value::Array(offers) => offers.iter().find_map(find_offer),
+        Value::Object(_) if has_price(offers) => Some(offers),
+        Value::Object(_) => offers.get("offers").and_then(find_offer),
+        _ => None,
+    }
+}
+
+/// Returns the elements matching the selector that are properties of the item itself,
+/// leaving out those of the items nested within it.
+fn item_properties<'a: 'b, 'b>(
+    item: ElementRef<'a>,
+    selector: &'b Selector,
+) -> impl Iterator<Item = ElementRef<'a>> + 'b {
+    item.select(selector).filter(move |element| {
+        element
+            .ancestors()
+            .filter_map(ElementRef::wrap)
+            .find(|ancestor| ancestor.value().attr("itemscope").is_some())
+            .is_some_and(|scope| scope.id() == item.id())
+    })
+}
+
+/// Microdata values live in `content`, `href` or `src` attributes, or in the element's text.
+/// Values read from the text are flagged, as they are formatted for display.
+fn microdata_value(element: ElementRef) -> Option<(String, bool)> {
+    let value = element.value();
+    let text = element
+        .text()
+        .flat_map(str::split_whitespace)
+        .collect::<Vec<_>>()
+        .join(" ");
+    ["content", "href", "src"]
+        .iter()
+        .find_map(|attribute| value.attr(attribute))
+        .map(|value| (value.trim().to_string(), false))
+        .or(Some((text, true)))
+        .filter(|(value, _)| !value.is_empty())
+}
+
+impl JsonLd {
+    fn get_url(&self) -> Result<Url, ScraperError> {
+        Url::parse(&self.url).map_err(|e| ScraperError::InvalidRequest {
+            context: self.error_context(),
+            reason: format!("Invalid url {:?}: {}", self.url, e),
+        })
+    }
+
+    fn selector(&self, selector_str: &str) -> Result<Selector, ScraperError> {
+        Selector::parse(selector_str).map_err(|e| ScraperError::Parse {
+            context: self.error_context(),
+            selector: selector_str.to_string(),
+            source: e.into(),
+        })
+    }
+
+    /// Reads the first `Product` found in the page's JSON-LD blocks.
+    /// Blocks that are not valid JSON are skipped, as pages often carry broken ones.
+    fn get_json_ld_product(&self, document: &Html) -> Result<ProductData, ScraperError> {
+        let selector = self.selector(JSON_LD_SELECTOR_STR)?;
+        let product = document
+            .select(&selector)
+            .filter_map(|script| {
+                serde_json::from_str::<Value>(&script.text().collect::<String>()).ok()
+            })
+            .find_map(|json_ld| find_product(&json_ld).map(ProductData::from_json_ld));
+
+        Ok(product.unwrap_or_default())
+    }
+
+    /// Reads the product's microdata (`itemprop` attributes within a schema.org Product).
+    /// The properties of the items nested within it, such as its brand or offers, are only read
+    /// from those items.
+    fn get_microdata_product(&self, document: &Html) -> Result<ProductData, ScraperError> {
+        let scope = self.selector("[itemtype*=\"schema.org/Product\"]")?;
+        let Some(product) = document.select(&scope).next() else {
+            return Ok(ProductData::default());
+        };
+
+        let item_prop_value =
+            |item: ElementRef, name: &str| -> Result<Option<(String, bool)>, ScraperError> {
+                let selector = self.selector(&format!("[itemprop~=\"{}\"]", name))?;
+                let value = item_properties(item, &selector).next();
+                Ok(value.and_then(microdata_value))
+            };
+        let item_prop = |item: ElementRef, name: &str| -> Result<Option<String>, ScraperError> {
+            Ok(item_prop_value(item, name)?.map(|(value, _)| value))
+        };
+        let nested_items = |name: &str| -> Result<Vec<ElementRef>, ScraperError> {
+            let selector = self.selector(&format!("[itemprop~=\"{}\"][itemscope]", name))?;
+            Ok(item_properties(product, &selector).collect())
+        };
+
+        let mut gtin = None;
+        for name in ["gtin", "gtin13", "gtin12", "gtin14", "gtin8"] {
+            gtin = gtin.or(item_prop(product, name)?);
+        }
+
+        // Reading the first offer with a price, or else the first offer, as with JSON-LD.
+        // Some pages put the offer properties on the product itself.
+        let item_price = |item| -> Result<Option<(String, bool)>, ScraperError> {
+            Ok(item_prop_value(item, "price")?.or(item_prop_value(item, "lowPrice")?))
+        };
+        let offers = nested_items("offers")?;
+        let mut offer = offers.first().copied();
+        let mut price = None;
+        for candidate in offers {
+            if let Some(candidate_price) = item_price(candidate)? {
+                (offer, price) = (Some(candidate), Some(candidate_price));
+                break;
+            }
+        }
+        let price = match price {
+            Some(price) => Some(price),
+            None => item_price(product)?,
+        };
+        let offer_prop = |name: &str| match offer {
+            Some(offer) => Ok(item_prop(offer, name)?.or(item_prop(product, name)?)),
+            None => item_prop(product, name),
+        };
+
+        // Guessing the decimal separator of prices displayed as text, e.g. "1.299,00 €"
+        let price_decimal_separator = match price {
+            Some((_, true)) => None,
+            _ => Some('.'),
+        };
+
+        // Reading the name of the brand item, if the brand is one
+        let brand = match nested_items("brand")?.first() {
+            Some(brand) => item_prop(*brand, "name")?,
+            None => item_prop(product, "brand")?,
+        };
+
+        Ok(ProductData {
+            name: item_prop(product, "name")?,
+            price: price.map(|(price, _)| price),
+            price_decimal_separator,
+            currency: offer_prop("priceCurrency")?,
+            availability: offer_prop("availability")?,
+            sku: item_prop(product, "sku")?,
+            gtin,
+            brand,
+            image: item_prop(product, "image")?,
+        })
+    }
+
+    /// Reads the OpenGraph product tags, e.g. `<meta property="product:price:amount" content="12.99">`.
+    fn get_open_graph_product(&self, document: &Html) -> Result<ProductData, ScraperError> {
+        let meta = |property: &str| {
+            self.find_css_attribute(
+                document,
+                &format!("meta[property=\"{}\"]", property),
+                "content",
+            )
+            .map_err(|source| ScraperError::Parse {
+                context: self.error_context(),
+                selector: property.to_string(),
+                source,
+            })
+        };
+
+        Ok(ProductData {
+            name: meta("og:title")?,
+            price: meta("product:price:amount")?.or(meta("og:price:amount")?),
+            price_decimal_separator: Some('.'),
+            currency: meta("product:price:currency")?.or(meta("og:price:currency")?),
+            availability: meta("product:availability")?.or(meta("og:availability")?),
+            sku: meta("product:retailer_item_id")?,
+            gtin: None,
+            brand: meta("product:brand")?,
+            image: meta("og:image")?,
+        })
+    }
+
+    fn get_product_information(
+        &self,
+        document: &Html,
+        url: &Url,
+    ) -> Result<ScrapingResult, ScraperError> {
+        // Combining the JSON-LD product with the microdata and OpenGraph fallbacks
+        let product = self
+            .get_json_ld_product(document)?
+            .or(self.get_microdata_product(document)?)
+            .or(self.get_open_graph_product(document)?);
+
+        let missing = |field: &str| ScraperError::Parse {
+            context: self.error_context(),
+            selector: format!("{} ({})", JSON_LD_SELECTOR_STR, field),
+            source: CssError::new("Product field not found."),
+        };
+
+        // Getting the name
+        let name = product.name.ok_or_else(|| missing("name"))?;
+
+        // Getting the availability, e.g. "https://schema.org/InStock"
+        let availability = product.availability.map(|availability_text| {
+            let availability = availability_text
+                .rsplit('/')
+                .next()
+                .unwrap_or_default()
+                .to_lowercase();
+            Availability {
+                in_stock: IN_STOCK_AVAILABILITIES.contains(&availability.as_str()),
+                availability_text,
+                ..Default::default()
+            }
+        });
+        let in_stock = availability.as_ref().is_none_or(|a| a.in_stock);
+
+        // Getting the price
+        // Out of stock products often have no offer price.
+        let currency = product
+            .currency
+            .or_else(|| Some(self.default_currency.to_owned()).filter(|c| !c.is_empty()))
+            .map(|currency| currency.to_uppercase());
+        let money = match (product.price, currency.as_deref()) {
+            (Some(raw_price), Some(currency)) => Some(
+                Money::parse(&raw_price, currency, product.price_decimal_separator).ok_or_else(
+                    || ScraperError::PriceFormat {
+                        context: self.error_context(),
+                        raw: raw_price.clone(),
+                    },
+                )?,
+            ),
+            (Some(_), None) => {
+                return Err(ScraperError::InvalidRequest {
+                    context: self.error_context(),
+                    reason: "No currency found on the page, and no default_currency given."
+                        .to_string(),
+                })
+            }
+            (None, _) if in_stock => return Err(missing("price")),
+            (None, _) => None,
+        };
+
+        // Adding the product identifiers to the request attributes
+        let mut attributes = self.get_attributes();
+        for (key, value) in [
+            ("sku", &product.sku),
+            ("gtin", &product.gtin),
+            ("brand", &product.brand),
+        ] {
+            if let Some(value) = value {
+                attributes.insert(key.to_string(), value.to_owned());
+            }
+        }
+
+        // Constructing the ScrapingResult
+        Ok(ScrapingResult {
+            source: self.get_source_name(),
+            utc_timestamp: self.get_current_utc_time(),
+            name,
+            identifier: match self.identifier.is_empty() {
+                true => product.sku.unwrap_or_else(|| self.get_identifier()),
+                false => self.get_identifier(),
+            },
+            price: money.as_ref().map_or(0.0, Money::as_f32),
+            status: ScrapingStatus::Success as i32,
+            failure: None,
+            request_id: self.get_request_id(),
+            availability,
+            currency: currency.unwrap_or_default(),
+            money: money.map(Into::into),
+            pricing: None,
+            image_url: product
+                .image
+                .map(|image| url.join(&image).map_or(image, |url| url.to_string()))
+                .unwrap_or_default(),
+            attributes,
+            metadata: self.get_result_metadata(),
+        })
+    }
+}
+
+impl scraping_traits::Source for JsonLd {
+    fn get_source_name(&self) -> String {
+        "json_ld".to_string()
+    }
+}
+
+impl BaseTraits for JsonLd {
+    fn error_context(&self) -> ErrorContext {
+        ErrorContext::product(self.get_source_name(), self.get_identifier())
+    }
+}
+
+#[async_trait]
+impl scraping_traits::Scraper for JsonLd {
+    fn get_unique_id(&self) -> String {
+        format!("{} - {}", self.get_source_name(), self.get_identifier())
+    }
+
+    fn get_identifier(&self) -> String {
+        match self.identifier.is_empty() {
+            true => self.url.to_owned(),
+            false => self.identifier.to_owned(),
+        }
+    }
+
+    fn get_target_domain(&self) -> Option<String> {
+        Url::parse(&self.url).ok()?.host_str().map(str::to_string)
+    }
+
+    request_accessors!();
+
+    async fn scrape(
+        &self,
+        client: &Client,
+    ) -> Result<scraping::results::ScrapingResult, ScraperError> {
+        // Constructing the request
+        let url = self.get_url()?;
+        let context = self.error_context().with_url(url.as_str());
+        let request = client
+            .get(url.clone())
+            .build()
+            .map_err(|e| ScraperError::from_request(context.clone(), e))?;
+
+        // Performing the request
+        let raw_html_string = self
+            .request(client, request, None, None)
+            .await?
+            .text()
+            .await
+            .map_err(|e| ScraperError::from_request(context, e))?;
+
+        // Parsing the response into a HTML Document
+        let document = Html::parse_document(&raw_html_string);
+
+        // Extracting the product from the structured data in the HTML Document
+        self.get_product_information(&document, &url)
+    }
+}
+
+/// Registers the JsonLd source under its source name.
+pub(crate) fn register(registry: &mut SourceRegistry) {
+    registry.register("json_ld", |source| match source {
+        RequestSource::JsonLd(mut request) => {
+            fill_request_id(&mut request.request_id);
+            Some(Box::new(request))
+        }
+        _ => None,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Url;
+    use scraper::Html;
+
+    use crate::{
+        errors::ScraperError,
+        scraping::{
+            requests::JsonLd,
+            results::{Money, ScrapingResult},
+        },
+    };
+
+    fn scrape(url: &str, html: &str) -> Result<ScrapingResult, ScraperError> {
+        let request = JsonLd {
+            url: url.to_string(),
+            ..Default::default()
+        };
+        let document = Html::parse_document(html);
+        request.get_product_information(&document, &Url::parse(url).unwrap())
+    }
+
+    fn money(amount_minor: i64, currency: &str) -> Option<Money> {
+        Some(Money {
+            amount_minor,
+            currency: currency.to_string(),
+        })
+    }
+
+    #[test]
+    fn finds_the_product_within_a_graph() {
+        let result = scrape(
+            "https://oakandiron.example/desk/walnut-organiser/",
+            include_str!("../../fixtures/json_ld/graph.html"),
+        )
+        .unwrap();
+
+        assert_eq!(result.name, "Walnut Desk Organiser");
+        assert_eq!(result.identifier, "OI-WDO-01");
+        assert_eq!(result.money, money(6450, "GBP"));
+        assert!(result.availability.unwrap().in_stock);
+        assert_eq!(result.attributes["gtin"], "5060123456784");
+        assert_eq!(result.attributes["brand"], "Oak & Iron");
+        assert_eq!(
+            result.image_url,
+            "https://oakandiron.example/media/walnut-organiser.jpg"
+        );
+    }
+
+    #[test]
+    fn reads_the_lowest_price_of_aggregate_offers() {
+        let url = "https://compare.example/tents/trailhead-2";
+        let result = scrape(
+            url,
+            include_str!("../../fixtures/json_ld/aggregate-offer.html"),
+        )
+        .unwrap();
+
+        assert_eq!(result.name, "Trailhead 2 Tent");
+        assert_eq!(result.identifier, url);
+        assert_eq!(result.money, money(18995, "USD"));
+        assert!(result.availability.unwrap().in_stock);
+        assert_eq!(result.attributes["brand"], "Summit Gear");
+        assert_eq!(
+            result.image_url,
+            "https://cdn.compare.example/trailhead-2.jpg"
+        );
+    }
+
+    #[test]
+    fn reads_microdata_without_the_properties_of_nested_items() {
+        let url = "https://kaffeehaus.example/duo";
+        let html = include_str!("../../fixtures/json_ld/microdata.html");
+        let result = scrape(url, html).unwrap();
+
+        // The brand, seller and similar product names are not the product's
+        assert_eq!(result.name, "Siebträgermaschine Duo");
+        assert_eq!(result.attributes["brand"], "Rocca");
+        assert_eq!(result.identifier, "KH-4471");
+        assert_eq!(result.money, money(129900, "EUR"));
+        assert_eq!(
+            result.availability.unwrap().availability_text,
+            "https://schema.org/InStock"
+        );
+        assert_eq!(result.image_url, "https://kaffeehaus.example/media/duo.jpg");
+    }
+
+    #[test]
+    fn guesses_the_decimal_separator_of_microdata_prices_read_from_text() {
+        let url = "https://kaffeehaus.example/duo";
+        let html = include_str!("../../fixtures/json_ld/microdata.html");
+        let price = |price_element: &str| {
+            let html = html.replace(r#"<span itemprop="price">1.299,00 €</span>"#, price_element);
+            scrape(url, &html).unwrap().money
+        };
+
+        assert_eq!(
+            price(r#"<span itemprop="price">1,299.00 €</span>"#),
+            money(129900, "EUR")
+        );
+        assert_eq!(
+            price(r#"<span itemprop="price">1.299 €</span>"#),
+            money(129900, "EUR")
+        );
+        // Machine-readable values always use `.`
+        assert_eq!(
+            price(r#"<meta itemprop="price" content="1299.00">"#),
+            money(129900, "EUR")
+        );
+    }
+
+    #[test]
+    fn falls_back_to_open_graph_tags() {
+        let result = scrape(
+            "https://fernandflax.example/products/linen-apron-sage",
+            include_str!("../../fixtures/json_ld/open-graph.html"),
+        )
+        .unwrap();
+
+        assert_eq!(result.name, "Linen Apron - Sage");
+        assert_eq!(result.identifier, "FF-APR-SAGE");
+        assert_eq!(result.money, money(2800, "GBP"));
+        let availability = result.availability.unwrap();
+        assert_eq!(availability.availability_text, "out of stock");
+        assert!(!availability.in_stock);
+        assert_eq!(result.attributes["brand"], "Fern & Flax");
+        assert_eq!(
+            result.image_url,
+            "https://fernandflax.example/cdn/linen-apron-sage.jpg"
+        );
+    }
+}
@@ -0,0 +1,410 @@
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode, Url};
+
+use crate::{
+    errors::{ErrorContext, ScraperError},
+    price::Money,
+    scraping::{
+        requests::{scraping_request::Source as RequestSource, Shopify},
+        results::{Availability, Pricing, ScrapingResult, ScrapingStatus},
+    },
+    scraping_traits::{self, BaseTraits, Scraper, Source},
+    sources::{fill_request_id, parse_store_url, SourceRegistry},
+};
+
+/// Title Shopify gives to the only variant of products without options.
+const DEFAULT_VARIANT_TITLE: &str = "Default Title";
+
+/// Product returned by `/products/<handle>.js`.
+/// Unlike `/products/<handle>.json`, it reports whether each variant is available.
+#[derive(Debug, serde::Deserialize)]
+struct ShopifyProduct {
+    title: String,
+    featured_image: Option<String>,
+    variants: Vec<ShopifyVariant>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ShopifyVariant {
+    id: u64,
+    title: String,
+    sku: Option<String>,
+    available: bool,
+    price: i64,                    // In cents of the store currency
+    compare_at_price: Option<i64>, // In cents of the store currency
+    featured_image: Option<ShopifyImage>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ShopifyImage {
+    src: String,
+}
+
+/// Cart returned by `/cart.js`, only read for the store currency.
+#[derive(Debug, serde::Deserialize)]
+struct ShopifyCart {
+    currency: String,
+}
+
+impl Shopify {
+    /// The store's root url.
+    fn get_store_url(&self) -> Result<Url, ScraperError> {
+        let invalid = |reason: String| ScraperError::InvalidRequest {
+            context: self.error_context(),
+            reason,
+        };
+        if self.store_domain.trim().is_empty() || self.handle.is_empty() {
+            return Err(invalid(
+                "Both store_domain and handle are required.".to_string(),
+            ));
+        }
+
+        parse_store_url(&self.store_domain).map_err(|e| {
+            invalid(format!(
+                "Invalid store_domain {:?}: {}",
+                self.store_domain, e
+            ))
+        })
+    }
+
+    /// The currency of the store's prices, from the request or else from the store's cart.
+    async fn get_currency(&self, client: &Client, store_url: &Url) -> Result<String, ScraperError> {
+        if !self.currency.is_empty() {
+            return Ok(self.currency.to_uppercase());
+        }
+
+        let cart_url = store_url.join("cart.js").expect("Valid relative url.");
+        let cart = self.fetch_json::<ShopifyCart>(client, cart_url).await?;
+        Ok(cart.currency.to_uppercase())
+    }
+
+    fn get_variant_result(
+        &self,
+        product: &ShopifyProduct,
+        variant: &ShopifyVariant,
+        currency: &str,
+        store_url: &Url,
+    ) -> Result<ScrapingResult, ScraperError> {
+        let from_cents = |cents: i64| {
+            Money::from_scaled(cents, 2, currency).ok_or_else(|| ScraperError::PriceFormat {
+                context: self.error_context(),
+                raw: format!("{} cents", cents),
+            })
+        };
+        let money = from_cents(variant.price)?;
+        let compare_at_money = variant.compare_at_price.map(from_cents).transpose()?;
+
+        // Naming the variant after its options, unless the product has none
+        let name = match variant.title.as_str() {
+            DEFAULT_VARIANT_TITLE => product.title.to_owned(),
+            variant_title => format!("{} - {}", product.title, variant_title),
+        };
+
+        // Getting the image of the variant, falling back to the product's.
+        // Shopify serves protocol-relative links, e.g. "//cdn.shopify.com/...".
+        let image_url = variant
+            .featured_image
+            .as_ref()
+            .map(|image| image.src.to_owned())
+            .or_else(|| product.featured_image.to_owned())
+            .map(|image| {
+                store_url
+                    .join(&image)
+                    .map_or(image, |image_url| image_url.to_string())
+            })
+            .unwrap_or_default();
+
+        // Adding the variant details to the request attributes
+        let mut attributes = self.get_attributes();
+        attributes.insert("variant_id".to_string(), variant.id.to_string());
+        attributes.insert("variant_title".to_string(), variant.title.to_owned());
+        if let Some(sku) = variant.sku.as_ref().filter(|sku| !sku.is_empty()) {
+            attributes.insert("sku".to_string(), sku.to_owned());
+        }
+        attributes.insert("price".to_string(), money.to_string());
+        if let Some(compare_at_money) = &compare_at_money {
+            attributes.insert("compare_at_price".to_string(), compare_at_money.to_string());
+        }
+        attributes.insert("available".to_string(), variant.available.to_string());
+
+        // Reporting the compare-at price as the list price, when the variant is on sale
+        let pricing = compare_at_money.map(|compare_at_money| Pricing {
+            savings_percentage: money
+                .savings_percentage(&compare_at_money)
+                .unwrap_or_default(),
+            list_price: Some(compare_at_money.into()),
+            ..Default::default()
+        });
+
+        Ok(ScrapingResult {
+            source: self.get_source_name(),
+            utc_timestamp: self.get_current_utc_time(),
+            name,
+            identifier: format!("{}:{}", self.handle, variant.id),
+            price: money.as_f32(),
+            status: ScrapingStatus::Success as i32,
+            failure: None,
+            request_id: self.get_request_id(),
+            availability: Some(Availability {
+                availability_text: match variant.available {
+                    true => "In stock".to_string(),
+                    false => "Sold out".to_string(),
+                },
+                in_stock: variant.available,
+                ..Default::default()
+            }),
+            currency: money.currency.clone(),
+            money: Some(money.into()),
+            pricing,
+            image_url,
+            attributes,
+            metadata: self.get_result_metadata(),
+        })
+    }
+}
+
+impl scraping_traits::Source for Shopify {
+    fn get_source_name(&self) -> String {
+        "shopify".to_string()
+    }
+}
+
+impl BaseTraits for Shopify {
+    fn error_context(&self) -> ErrorContext {
+        ErrorContext::product(self.get_source_name(), self.get_identifier())
+    }
+
+    /// Unknown handles are not going to appear by retrying.
+    fn should_retry_status(&self, status: StatusCode) -> bool {
+        status != StatusCode::NOT_FOUND
+    }
+}
+
+#[async_trait]
+impl scraping_traits::Scraper for Shopify {
+    fn get_unique_id(&self) -> String {
+        format!(
+            "{} - {}/{}",
+            self.get_source_name(),
+            self.store_domain,
+            self.get_identifier()
+        )
+    }
+
+    fn get_identifier(&self) -> String {
+        match self.variant_id {
+            0 => self.handle.to_owned(),
+            variant_id => format!("{}:{}", self.handle, variant_id),
+        }
+    }
+
+    fn get_target_domain(&self) -> Option<String> {
+        self.get_store_url().ok()?.host_str().map(str::to_string)
+    }
+
+    request_accessors!();
+
+    /// Produces the result of the requested variant, or of the first variant if none was requested.
+    async fn scrape(&self, client: &Client) -> Result<ScrapingResult, ScraperError> {
+        let mut results = self.scrape_all(client).await?;
+        Ok(results.remove(0)) // scrape_all never returns an empty list.
+    }
+
+    async fn scrape_all(&self, client: &Client) -> Result<Vec<ScrapingResult>, ScraperError> {
+        // Fetching the product
+        let store_url = self.get_store_url()?;
+        let product_url = store_url
+            .join(&format!("products/{}.js", self.handle))
+            .map_err(|e| ScraperError::InvalidRequest {
+                context: self.error_context(),
+                reason: format!("Invalid handle {:?}: {}", self.handle, e),
+            })?;
+        let product = self
+            .fetch_json::<ShopifyProduct>(client, product_url)
+            .await?;
+
+        // Getting the currency of the prices
+        let currency = self.get_currency(client, &store_url).await?;
+
+        // Selecting the variants to report
+        let variants = product
+            .variants
+            .iter()
+            .filter(|variant| self.variant_id == 0 || variant.id == self.variant_id)
+            .collect::<Vec<_>>();
+        if variants.is_empty() {
+            return Err(ScraperError::InvalidRequest {
+                context: self.error_context(),
+                reason: match self.variant_id {
+                    0 => "The product has no variants.".to_string(),
+                    variant_id => format!("The product has no variant {}.", variant_id),
+                },
+            });
+        }
+
+        // Constructing one ScrapingResult per variant
+        variants
+            .into_iter()
+            .map(|variant| self.get_variant_result(&product, variant, &currency, &store_url))
+            .collect()
+    }
+}
+
+/// Registers the Shopify source under its source name.
+pub(crate) fn register(registry: &mut SourceRegistry) {
+    registry.register("shopify", |source| match source {
+        RequestSource::Shopify(mut request) => {
+            fill_request_id(&mut request.request_id);
+            Some(Box::new(request))
+        }
+        _ => None,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ShopifyCart, ShopifyProduct};
+    use crate::scraping::{
+        requests::Shopify,
+        results::{Money, ScrapingResult},
+    };
+
+    fn product(json: &str) -> ShopifyProduct {
+        serde_json::from_str(json).expect("Valid fixture.")
+    }
+
+    fn request(handle: &str) -> Shopify {
+        Shopify {
+            store_domain: "loomandco.example".to_string(),
+            handle: handle.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Results of every variant, as `scrape_all` produces them.
+    fn results(request: &Shopify, product: &ShopifyProduct, currency: &str) -> Vec<ScrapingResult> {
+        let store_url = request.get_store_url().unwrap();
+        product
+            .variants
+            .iter()
+            .map(|variant| request.get_variant_result(product, variant, currency, &store_url))
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    fn money(amount_minor: i64, currency: &str) -> Option<Money> {
+        Some(Money {
+            amount_minor,
+            currency: currency.to_string(),
+        })
+    }
+
+    #[test]
+    fn names_products_without_options_after_the_product() {
+        let tote = product(include_str!(
+            "../../fixtures/shopify/products-canvas-tote.js"
+        ));
+        let results = results(&request("canvas-tote"), &tote, "USD");
+
+        assert_eq!(results.len(), 1);
+        let result = &results[0];
+        assert_eq!(result.name, "Canvas Tote");
+        assert_eq!(result.identifier, "canvas-tote:44012345678901");
+        assert_eq!(result.attributes["variant_title"], "Default Title");
+        assert_eq!(result.attributes["sku"], "TOTE-NAT");
+        // Falling back to the product image, with the store's scheme
+        assert_eq!(
+            result.image_url,
+            "https://loomandco.example/cdn/shop/files/canvas-tote.jpg?v=1712345678"
+        );
+    }
+
+    #[test]
+    fn reports_one_result_per_variant() {
+        let hoodie = product(include_str!(
+            "../../fixtures/shopify/products-merino-hoodie.js"
+        ));
+        let results = results(&request("merino-hoodie"), &hoodie, "EUR");
+
+        let names = results.iter().map(|r| r.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            ["Merino Hoodie - S / Black", "Merino Hoodie - M / Black"]
+        );
+        assert_eq!(results[0].money, money(12900, "EUR"));
+        assert_eq!(results[0].pricing, None);
+        assert!(results[0].availability.as_ref().unwrap().in_stock);
+        assert_eq!(
+            results[0].image_url,
+            "https://loomandco.example/cdn/shop/files/merino-hoodie-black.jpg?v=1712345679"
+        );
+
+        let sold_out = results[1].availability.as_ref().unwrap();
+        assert!(!sold_out.in_stock);
+        assert_eq!(sold_out.availability_text, "Sold out");
+        assert!(!results[1].attributes.contains_key("sku"));
+        assert_eq!(
+            results[1].image_url,
+            "https://loomandco.example/cdn/shop/files/merino-hoodie.jpg?v=1712345679"
+        );
+    }
+
+    #[test]
+    fn reports_the_compare_at_price_as_the_list_price() {
+        let tote = product(include_str!(
+            "../../fixtures/shopify/products-canvas-tote.js"
+        ));
+        let result = &results(&request("canvas-tote"), &tote, "USD")[0];
+
+        assert_eq!(result.money, money(2400, "USD"));
+        assert_eq!(result.price, 24.0);
+        let pricing = result.pricing.as_ref().unwrap();
+        assert_eq!(pricing.list_price, money(3200, "USD"));
+        assert_eq!(pricing.savings_percentage, 25);
+        assert_eq!(result.attributes["compare_at_price"], "32.00 USD");
+    }
+
+    #[test]
+    fn rescales_cents_to_the_store_currency() {
+        let cart: ShopifyCart =
+            serde_json::from_str(include_str!("../../fixtures/shopify/cart.js")).unwrap();
+        let matcha = product(include_str!(
+            "../../fixtures/shopify/products-matcha-set.js"
+        ));
+        let request = Shopify {
+            store_domain: "https://chaya.example".to_string(),
+            ..request("matcha-set")
+        };
+        let result = &results(&request, &matcha, &cart.currency)[0];
+
+        // Shopify reports ¥4,800 as 480000 "cents"
+        assert_eq!(result.name, "抹茶セット");
+        assert_eq!(result.money, money(4800, "JPY"));
+        assert_eq!(result.price, 4800.0);
+        let pricing = result.pricing.as_ref().unwrap();
+        assert_eq!(pricing.list_price, money(5500, "JPY"));
+        assert_eq!(pricing.savings_percentage, 13);
+    }
+
+    #[test]
+    fn parses_store_domains_with_or_without_a_scheme() {
+        let store_url = |store_domain: &str| {
+            Shopify {
+                store_domain: store_domain.to_string(),
+                ..request("canvas-tote")
+            }
+            .get_store_url()
+            .map(|url| url.to_string())
+        };
+
+        assert_eq!(
+            store_url("loomandco.example/").unwrap(),
+            "https://loomandco.example/"
+        );
+        assert_eq!(
+            store_url("http://localhost:8000").unwrap(),
+            "http://localhost:8000/"
+        );
+        assert!(store_url(" ").is_err());
+    }
+}
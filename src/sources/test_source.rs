@@ -1,31 +1,368 @@
-use std::error::Error;
+use std::time::Duration;
 
 use async_trait::async_trait;
-use reqwest::Client;
+use reqwest::{Client, Url};
 
 use crate::{
-    scraping::{self, requests::Test},
-    scraping_traits::{self, BaseTraits},
+    errors::{CssError, ErrorContext, ScraperError},
+    price::Money,
+    scraping::{
+        self,
+        requests::{scraping_request::Source as RequestSource, Test},
+        results::{ScrapingResult, ScrapingStatus},
+    },
+    scraping_traits::{self, BaseTraits, Scraper, Source},
+    sources::{fill_request_id, SourceRegistry},
 };
 
+/// Longest delay a spec can ask for, so that a request cannot hold on to a task for good.
+const MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// What a `Test` request should produce, parsed from its `content`.
+///
+/// The content is a list of `key=value` pairs separated by `;`, e.g.
+/// `id=widget;name=Widget;price=12.34;currency=USD;delay_ms=200`. Supported keys:
+/// - `id`, `name`, `price` and `currency` describe the product. Content without any `=` is used as the name.
+/// - `url` fetches the product from a JSON document such as `{"name": "Widget", "price": "12.34"}`.
+///   Values given in the content take precedence over the fetched ones.
+/// - `delay_ms` waits before producing the result, for at most a minute.
+/// - `fail` raises an error of the given kind: `http_status` (with `status`, default 500), `parse`,
+///   `price_format`, `blocked` or `invalid_request`.
+#[derive(Debug, Default)]
+struct TestSpec {
+    id: Option<String>,
+    name: Option<String>,
+    price: Option<String>,
+    currency: Option<String>,
+    url: Option<String>,
+    delay: Option<Duration>,
+    fail: Option<String>,
+    status: Option<u16>,
+}
+
+/// Product fetched from the `url` of a spec.
+#[derive(Debug, serde::Deserialize)]
+struct TestProduct {
+    name: Option<String>,
+    price: Option<serde_json::Value>, // Either a number or a displayed price
+    currency: Option<String>,
+}
+
+impl TestSpec {
+    fn parse(content: &str) -> Result<Self, String> {
+        let mut spec = TestSpec::default();
+        if !content.contains('=') {
+            spec.name = Some(content.trim().to_string()).filter(|name| !name.is_empty());
+            return Ok(spec);
+        }
+
+        for pair in content.split(';').filter(|pair| !pair.trim().is_empty()) {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("Expected key=value, got {:?}.", pair))?;
+            let value = value.trim().to_string();
+            match key.trim() {
+                "id" => spec.id = Some(value),
+                "name" => spec.name = Some(value),
+                "price" => spec.price = Some(value),
+                "currency" => spec.currency = Some(value),
+                "url" => spec.url = Some(value),
+                "delay_ms" => {
+                    let delay = value
+                        .parse::<u64>()
+                        .map(Duration::from_millis)
+                        .map_err(|_| format!("Invalid delay_ms {:?}.", value))?;
+                    if delay > MAX_DELAY {
+                        return Err(format!(
+                            "delay_ms {:?} exceeds {}ms.",
+                            value,
+                            MAX_DELAY.as_millis()
+                        ));
+                    }
+                    spec.delay = Some(delay);
+                }
+                "fail" => spec.fail = Some(value),
+                "status" => {
+                    let status = value
+                        .parse::<u16>()
+                        .map_err(|_| format!("Invalid status {:?}.", value))?;
+                    spec.status = Some(status);
+                }
+                key => return Err(format!("Unknown key {:?}.", key)),
+            }
+        }
+
+        Ok(spec)
+    }
+}
+
+impl Test {
+    fn get_spec(&self) -> Result<TestSpec, ScraperError> {
+        TestSpec::parse(&self.content).map_err(|reason| ScraperError::InvalidRequest {
+            context: self.error_context(),
+            reason,
+        })
+    }
+
+    /// Raises the failure requested by the spec, if any.
+    fn injected_failure(&self, spec: &TestSpec) -> Option<ScraperError> {
+        let context = self.error_context();
+        let error = match spec.fail.as_deref()? {
+            "http_status" => ScraperError::HttpStatus {
+                context,
+                status: spec.status.unwrap_or(500),
+                attempts: 1,
+            },
+            "parse" => ScraperError::Parse {
+                context,
+                selector: "#productTitle".to_string(),
+                source: CssError::new("Failed to find Css Node."),
+            },
+            "price_format" => ScraperError::PriceFormat {
+                context,
+                raw: spec.price.clone().unwrap_or_default(),
+            },
+            "blocked" => ScraperError::Blocked {
+                context,
+                reason: "Injected block.".to_string(),
+            },
+            "invalid_request" => ScraperError::InvalidRequest {
+                context,
+                reason: "Injected invalid request.".to_string(),
+            },
+            kind => ScraperError::InvalidRequest {
+                context,
+                reason: format!("Unknown failure kind {:?}.", kind),
+            },
+        };
+        Some(error)
+    }
+
+    async fn fetch_product(&self, client: &Client, url: &str) -> Result<TestProduct, ScraperError> {
+        let context = self.error_context().with_url(url);
+        let request = client
+            .get(url)
+            .build()
+            .map_err(|e| ScraperError::from_request(context.clone(), e))?;
+
+        self.request(client, request, Some(1), None)
+            .await?
+            .json::<TestProduct>()
+            .await
+            .map_err(|e| ScraperError::from_request(context, e))
+    }
+}
+
 impl scraping_traits::Source for scraping::requests::Test {
     fn get_source_name(&self) -> String {
         "test".to_string()
     }
 }
 
-impl BaseTraits for Test {}
+impl BaseTraits for Test {
+    fn error_context(&self) -> ErrorContext {
+        ErrorContext::product(self.get_source_name(), self.get_identifier())
+    }
+}
 
 #[async_trait]
 impl scraping_traits::Scraper for Test {
     fn get_unique_id(&self) -> String {
-        format!("Test-payload")
+        format!("{} - {}", self.get_source_name(), self.get_identifier())
+    }
+
+    fn get_identifier(&self) -> String {
+        TestSpec::parse(&self.content)
+            .ok()
+            .and_then(|spec| spec.id)
+            .unwrap_or_else(|| self.content.to_owned())
+    }
+
+    fn get_target_domain(&self) -> Option<String> {
+        let url = TestSpec::parse(&self.content).ok()?.url?;
+        Url::parse(&url).ok()?.host_str().map(str::to_string)
+    }
+
+    request_accessors!();
+
+    /// Produces a result from the request content alone, without touching any real site.
+    async fn scrape(&self, client: &Client) -> Result<ScrapingResult, ScraperError> {
+        // Parsing the spec
+        let spec = self.get_spec()?;
+
+        // Simulating a slow site
+        if let Some(delay) = spec.delay {
+            tokio::time::sleep(delay).await;
+        }
+
+        // Raising the injected failure
+        if let Some(error) = self.injected_failure(&spec) {
+            return Err(error);
+        }
+
+        // Fetching the product, if a url was given
+        let fetched = match &spec.url {
+            Some(url) => Some(self.fetch_product(client, url).await?),
+            None => None,
+        };
+
+        // Combining the spec with the fetched product
+        let name = spec
+            .name
+            .or_else(|| fetched.as_ref()?.name.clone())
+            .unwrap_or_else(|| "Test product".to_string());
+        let currency = spec
+            .currency
+            .or_else(|| fetched.as_ref()?.currency.clone())
+            .unwrap_or_else(|| "USD".to_string());
+        let raw_price = spec
+            .price
+            .or_else(|| match fetched.as_ref()?.price.as_ref()? {
+                serde_json::Value::String(price) => Some(price.clone()),
+                price => Some(price.to_string()),
+            })
+            .unwrap_or_else(|| "0".to_string());
+        let money = Money::parse(&raw_price, &currency, Some('.')).ok_or_else(|| {
+            ScraperError::PriceFormat {
+                context: self.error_context(),
+                raw: raw_price.clone(),
+            }
+        })?;
+
+        // Constructing the ScrapingResult
+        Ok(ScrapingResult {
+            source: self.get_source_name(),
+            utc_timestamp: self.get_current_utc_time(),
+            name,
+            identifier: self.get_identifier(),
+            price: money.as_f32(),
+            status: ScrapingStatus::Success as i32,
+            failure: None,
+            request_id: self.get_request_id(),
+            availability: None,
+            currency: money.currency.clone(),
+            money: Some(money.into()),
+            pricing: None,
+            image_url: String::new(),
+            attributes: self.get_attributes(),
+            metadata: self.get_result_metadata(),
+        })
+    }
+}
+
+/// Registers the Test source under its source name.
+pub(crate) fn register(registry: &mut SourceRegistry) {
+    registry.register("test", |source| match source {
+        RequestSource::Test(mut request) => {
+            fill_request_id(&mut request.request_id);
+            Some(Box::new(request))
+        }
+        _ => None,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::TestSpec;
+    use crate::{errors::ScraperError, scraping::requests::Test, scraping_traits::Scraper};
+
+    fn request(content: &str) -> Test {
+        Test {
+            content: content.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn failure(content: &str) -> Option<ScraperError> {
+        let request = request(content);
+        request.injected_failure(&request.get_spec().unwrap())
+    }
+
+    #[test]
+    fn parses_key_value_pairs() {
+        let spec = TestSpec::parse(
+            "id=widget; name=Widget ;price=12.34;currency=USD;url=http://localhost:8000/widget.json;delay_ms=200;",
+        )
+        .unwrap();
+
+        assert_eq!(spec.id.as_deref(), Some("widget"));
+        assert_eq!(spec.name.as_deref(), Some("Widget"));
+        assert_eq!(spec.price.as_deref(), Some("12.34"));
+        assert_eq!(spec.currency.as_deref(), Some("USD"));
+        assert_eq!(
+            spec.url.as_deref(),
+            Some("http://localhost:8000/widget.json")
+        );
+        assert_eq!(spec.delay, Some(Duration::from_millis(200)));
+        assert_eq!(spec.fail, None);
+    }
+
+    #[test]
+    fn uses_content_without_pairs_as_the_name() {
+        let spec = TestSpec::parse("  Widget  ").unwrap();
+        assert_eq!(spec.name.as_deref(), Some("Widget"));
+        assert_eq!(spec.id, None);
+
+        assert_eq!(TestSpec::parse("").unwrap().name, None);
+        assert_eq!(request("Widget").get_identifier(), "Widget");
+        assert_eq!(request("id=widget;name=Widget").get_identifier(), "widget");
+    }
+
+    #[test]
+    fn caps_the_delay() {
+        let spec = TestSpec::parse("delay_ms=60000").unwrap();
+        assert_eq!(spec.delay, Some(Duration::from_secs(60)));
+
+        assert!(TestSpec::parse("delay_ms=60001").is_err());
+        assert!(TestSpec::parse("delay_ms=-1").is_err());
+        assert!(TestSpec::parse("delay_ms=soon").is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_content() {
+        assert!(TestSpec::parse("name=Widget;colour=red").is_err());
+        assert!(TestSpec::parse("name=Widget;price").is_err());
+        assert!(TestSpec::parse("fail=http_status;status=999999").is_err());
+
+        let error = request("name=Widget;colour=red").get_spec().unwrap_err();
+        assert_eq!(error.kind(), "invalid_request");
+    }
+
+    #[test]
+    fn injects_each_failure_kind() {
+        assert!(failure("name=Widget").is_none());
+
+        for kind in [
+            "http_status",
+            "parse",
+            "price_format",
+            "blocked",
+            "invalid_request",
+        ] {
+            let error = failure(&format!("name=Widget;fail={}", kind)).unwrap();
+            assert_eq!(error.kind(), kind);
+        }
+
+        // Unknown kinds are reported as invalid requests
+        let error = failure("fail=meltdown").unwrap();
+        assert_eq!(error.kind(), "invalid_request");
     }
 
-    async fn scrape(
-        &self,
-        client: &Client,
-    ) -> Result<scraping::results::ScrapingResult, Box<dyn Error + Send>> {
-        todo!()
+    #[test]
+    fn injects_the_requested_status() {
+        match failure("fail=http_status").unwrap() {
+            ScraperError::HttpStatus { status, .. } => assert_eq!(status, 500),
+            error => panic!("Unexpected error {:?}", error),
+        }
+        match failure("fail=http_status;status=429").unwrap() {
+            ScraperError::HttpStatus { status, .. } => assert_eq!(status, 429),
+            error => panic!("Unexpected error {:?}", error),
+        }
+        match failure("price=12,34,56;fail=price_format").unwrap() {
+            ScraperError::PriceFormat { raw, .. } => assert_eq!(raw, "12,34,56"),
+            error => panic!("Unexpected error {:?}", error),
+        }
     }
 }
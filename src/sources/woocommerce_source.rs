@@ -0,0 +1,452 @@
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode, Url};
+
+use crate::{
+    errors::{ErrorContext, ScraperError},
+    price::Money,
+    scraping::{
+        requests::{scraping_request::Source as RequestSource, WooCommerce},
+        results::{Availability, Pricing, ScrapingResult, ScrapingStatus},
+    },
+    scraping_traits::{self, BaseTraits, Scraper, Source},
+    sources::{fill_request_id, parse_store_url, SourceRegistry},
+};
+
+/// Path of the Store API products endpoint, relative to the root of the WordPress site.
+const PRODUCTS_PATH: &str = "wp-json/wc/store/products";
+
+/// Product returned by the Store API, either a product or one of its variations.
+/// Recorded responses live in `fixtures/woocommerce`.
+#[derive(Debug, serde::Deserialize)]
+struct WcProduct {
+    id: u64,
+    name: String,
+    #[serde(default)]
+    sku: String,
+    prices: WcPrices,
+    is_in_stock: bool,
+    stock_availability: Option<WcStockAvailability>,
+    #[serde(default)]
+    images: Vec<WcImage>,
+    #[serde(default)]
+    variations: Vec<WcVariation>,
+}
+
+/// Prices are strings of minor units, e.g. "1999" for 19.99 with a `currency_minor_unit` of 2.
+#[derive(Debug, serde::Deserialize)]
+struct WcPrices {
+    price: String,
+    #[serde(default)]
+    regular_price: String,
+    currency_code: String,
+    currency_minor_unit: u32,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct WcStockAvailability {
+    text: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct WcImage {
+    src: String,
+}
+
+/// Variation listed on its parent product. Its prices are only given when fetched by id.
+#[derive(Debug, serde::Deserialize)]
+struct WcVariation {
+    id: u64,
+    #[serde(default)]
+    attributes: Vec<WcVariationAttribute>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct WcVariationAttribute {
+    name: String,
+    value: String,
+}
+
+impl WcVariation {
+    /// Describes the variation by its attributes, e.g. "Color: Red, Size: L".
+    fn get_description(&self) -> String {
+        self.attributes
+            .iter()
+            .map(|attribute| format!("{}: {}", attribute.name, attribute.value))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl WooCommerce {
+    /// The root of the WordPress site.
+    fn get_store_url(&self) -> Result<Url, ScraperError> {
+        let invalid = |reason: String| ScraperError::InvalidRequest {
+            context: self.error_context(),
+            reason,
+        };
+        if self.store_url.trim().is_empty() || (self.product_id == 0 && self.slug.is_empty()) {
+            return Err(invalid(
+                "A store_url and either a product_id or a slug are required.".to_string(),
+            ));
+        }
+
+        parse_store_url(&self.store_url)
+            .map_err(|e| invalid(format!("Invalid store_url {:?}: {}", self.store_url, e)))
+    }
+
+    /// Fetches a product or variation by id.
+    async fn fetch_product(
+        &self,
+        client: &Client,
+        store_url: &Url,
+        id: u64,
+    ) -> Result<WcProduct, ScraperError> {
+        let product_url = store_url
+            .join(&format!("{}/{}", PRODUCTS_PATH, id))
+            .expect("Valid relative url.");
+        self.fetch_json(client, product_url).await
+    }
+
+    /// Fetches the requested product, by id or else by slug.
+    async fn fetch_requested_product(
+        &self,
+        client: &Client,
+        store_url: &Url,
+    ) -> Result<WcProduct, ScraperError> {
+        if self.product_id != 0 {
+            return self.fetch_product(client, store_url, self.product_id).await;
+        }
+
+        // Searching the products by slug
+        let mut search_url = store_url.join(PRODUCTS_PATH).expect("Valid relative url.");
+        search_url.query_pairs_mut().append_pair("slug", &self.slug);
+        self.fetch_json::<Vec<WcProduct>>(client, search_url)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| ScraperError::InvalidRequest {
+                context: self.error_context(),
+                reason: format!("No product found with the slug {:?}.", self.slug),
+            })
+    }
+
+    fn parse_price(&self, raw: &str, prices: &WcPrices) -> Result<Money, ScraperError> {
+        let amount = raw.parse::<i64>().map_err(|_| ScraperError::PriceFormat {
+            context: self.error_context(),
+            raw: raw.to_string(),
+        })?;
+        Money::from_scaled(amount, prices.currency_minor_unit, &prices.currency_code).ok_or_else(
+            || ScraperError::PriceFormat {
+                context: self.error_context(),
+                raw: format!("{} ({} decimals)", raw, prices.currency_minor_unit),
+            },
+        )
+    }
+
+    /// Builds the result of a simple product, or of a variation along with its parent product.
+    fn get_product_result(
+        &self,
+        product: &WcProduct,
+        variation: Option<(&WcProduct, &WcVariation)>,
+    ) -> Result<ScrapingResult, ScraperError> {
+        // Reading the variation over its parent product, if there is one
+        let (item, description) = match variation {
+            Some((item, variation)) => (item, Some(variation.get_description())),
+            None => (product, None),
+        };
+
+        // Getting the prices
+        let money = self.parse_price(&item.prices.price, &item.prices)?;
+        let regular_money = match item.prices.regular_price.as_str() {
+            "" => None,
+            regular_price => Some(self.parse_price(regular_price, &item.prices)?),
+        };
+
+        // Naming the variation after its attributes
+        let name = match description.as_deref() {
+            Some(description) if !description.is_empty() => {
+                format!("{} - {}", product.name, description)
+            }
+            _ => product.name.to_owned(),
+        };
+
+        // Adding the product details to the request attributes
+        let mut attributes = self.get_attributes();
+        attributes.insert("product_id".to_string(), product.id.to_string());
+        if let Some(description) = description {
+            attributes.insert("variation_id".to_string(), item.id.to_string());
+            attributes.insert("variation".to_string(), description);
+        }
+        if !item.sku.is_empty() {
+            attributes.insert("sku".to_string(), item.sku.to_owned());
+        }
+
+        // Reporting the regular price as the list price, when the product is on sale
+        let pricing = regular_money
+            .filter(|regular_money| regular_money.amount_minor > money.amount_minor)
+            .map(|regular_money| Pricing {
+                savings_percentage: money.savings_percentage(&regular_money).unwrap_or_default(),
+                list_price: Some(regular_money.into()),
+                ..Default::default()
+            });
+
+        // Constructing the ScrapingResult
+        Ok(ScrapingResult {
+            source: self.get_source_name(),
+            utc_timestamp: self.get_current_utc_time(),
+            name,
+            identifier: match variation {
+                Some(_) => format!("{}:{}", product.id, item.id),
+                None => product.id.to_string(),
+            },
+            price: money.as_f32(),
+            status: ScrapingStatus::Success as i32,
+            failure: None,
+            request_id: self.get_request_id(),
+            availability: Some(Availability {
+                availability_text: item
+                    .stock_availability
+                    .as_ref()
+                    .map(|stock_availability| stock_availability.text.to_owned())
+                    .filter(|text| !text.is_empty())
+                    .unwrap_or_else(|| match item.is_in_stock {
+                        true => "In stock".to_string(),
+                        false => "Out of stock".to_string(),
+                    }),
+                in_stock: item.is_in_stock,
+                ..Default::default()
+            }),
+            currency: money.currency.clone(),
+            money: Some(money.into()),
+            pricing,
+            image_url: item
+                .images
+                .first()
+                .or_else(|| product.images.first())
+                .map(|image| image.src.to_owned())
+                .unwrap_or_default(),
+            attributes,
+            metadata: self.get_result_metadata(),
+        })
+    }
+}
+
+impl scraping_traits::Source for WooCommerce {
+    fn get_source_name(&self) -> String {
+        "woocommerce".to_string()
+    }
+}
+
+impl BaseTraits for WooCommerce {
+    fn error_context(&self) -> ErrorContext {
+        ErrorContext::product(self.get_source_name(), self.get_identifier())
+    }
+
+    /// Unknown products are not going to appear by retrying.
+    fn should_retry_status(&self, status: StatusCode) -> bool {
+        status != StatusCode::NOT_FOUND
+    }
+}
+
+#[async_trait]
+impl scraping_traits::Scraper for WooCommerce {
+    fn get_unique_id(&self) -> String {
+        format!(
+            "{} - {}/{}",
+            self.get_source_name(),
+            self.store_url,
+            self.get_identifier()
+        )
+    }
+
+    fn get_identifier(&self) -> String {
+        match self.product_id {
+            0 => self.slug.to_owned(),
+            product_id => product_id.to_string(),
+        }
+    }
+
+    fn get_target_domain(&self) -> Option<String> {
+        self.get_store_url().ok()?.host_str().map(str::to_string)
+    }
+
+    request_accessors!();
+
+    /// Produces the result of the product, or of its first variation for variable products.
+    async fn scrape(&self, client: &Client) -> Result<ScrapingResult, ScraperError> {
+        let mut results = self.scrape_all(client).await?;
+        Ok(results.remove(0)) // scrape_all never returns an empty list.
+    }
+
+    async fn scrape_all(&self, client: &Client) -> Result<Vec<ScrapingResult>, ScraperError> {
+        // Fetching the product
+        let store_url = self.get_store_url()?;
+        let product = self.fetch_requested_product(client, &store_url).await?;
+
+        // Simple products have no variations to fetch
+        if product.variations.is_empty() {
+            return Ok(vec![self.get_product_result(&product, None)?]);
+        }
+
+        // Fetching the prices of each variation
+        let mut results = Vec::with_capacity(product.variations.len());
+        for variation in &product.variations {
+            let item = self.fetch_product(client, &store_url, variation.id).await?;
+            results.push(self.get_product_result(&product, Some((&item, variation)))?);
+        }
+
+        Ok(results)
+    }
+}
+
+/// Registers the WooCommerce source under its source name.
+pub(crate) fn register(registry: &mut SourceRegistry) {
+    registry.register("woocommerce", |source| match source {
+        RequestSource::WooCommerce(mut request) => {
+            fill_request_id(&mut request.request_id);
+            Some(Box::new(request))
+        }
+        _ => None,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{WcProduct, WooCommerce};
+    use crate::{
+        errors::ScraperError,
+        scraping::results::{Money, ScrapingResult},
+    };
+
+    fn product(json: &str) -> WcProduct {
+        serde_json::from_str(json).expect("Valid fixture.")
+    }
+
+    fn request(product_id: u64) -> WooCommerce {
+        WooCommerce {
+            store_url: "https://store.example.com".to_string(),
+            product_id,
+            ..Default::default()
+        }
+    }
+
+    fn money(amount_minor: i64, currency: &str) -> Option<Money> {
+        Some(Money {
+            amount_minor,
+            currency: currency.to_string(),
+        })
+    }
+
+    fn list_price(result: &ScrapingResult) -> Option<Money> {
+        result.pricing.as_ref()?.list_price.clone()
+    }
+
+    #[test]
+    fn reads_a_simple_product_on_sale() {
+        let beanie = product(include_str!("../../fixtures/woocommerce/product-34.json"));
+        let result = request(34).get_product_result(&beanie, None).unwrap();
+
+        assert_eq!(result.name, "Beanie");
+        assert_eq!(result.identifier, "34");
+        assert_eq!(result.money, money(1800, "USD"));
+        assert_eq!(result.price, 18.0);
+        assert_eq!(list_price(&result), money(2000, "USD"));
+        assert_eq!(result.pricing.as_ref().unwrap().savings_percentage, 10);
+        assert_eq!(result.attributes["sku"], "woo-beanie");
+        assert!(result.availability.unwrap().in_stock);
+    }
+
+    #[test]
+    fn reads_the_product_found_by_slug() {
+        let products: Vec<WcProduct> = serde_json::from_str(include_str!(
+            "../../fixtures/woocommerce/products-slug-beanie.json"
+        ))
+        .unwrap();
+        let request = WooCommerce {
+            slug: "beanie".to_string(),
+            ..request(0)
+        };
+        let result = request.get_product_result(&products[0], None).unwrap();
+
+        assert_eq!(result.identifier, "34");
+        assert_eq!(result.attributes["product_id"], "34");
+        assert_eq!(result.money, money(1800, "USD"));
+    }
+
+    #[test]
+    fn names_variations_after_their_attributes() {
+        let hoodie = product(include_str!("../../fixtures/woocommerce/product-40.json"));
+        let blue = product(include_str!("../../fixtures/woocommerce/product-41.json"));
+        let result = request(40)
+            .get_product_result(&hoodie, Some((&blue, &hoodie.variations[0])))
+            .unwrap();
+
+        assert_eq!(result.name, "Hoodie - Color: blue, Size: medium");
+        assert_eq!(result.identifier, "40:41");
+        assert_eq!(result.money, money(4200, "USD"));
+        assert_eq!(list_price(&result), money(4500, "USD"));
+        assert_eq!(result.pricing.as_ref().unwrap().savings_percentage, 7);
+        assert_eq!(result.attributes["variation_id"], "41");
+        assert_eq!(result.attributes["sku"], "woo-hoodie-blue-m");
+        assert!(result.image_url.ends_with("hoodie-blue.jpg"));
+    }
+
+    #[test]
+    fn reports_out_of_stock_variations() {
+        let hoodie = product(include_str!("../../fixtures/woocommerce/product-40.json"));
+        let red = product(include_str!("../../fixtures/woocommerce/product-42.json"));
+        let result = request(40)
+            .get_product_result(&hoodie, Some((&red, &hoodie.variations[1])))
+            .unwrap();
+
+        let availability = result.availability.unwrap();
+        assert!(!availability.in_stock);
+        assert_eq!(availability.availability_text, "Out of stock");
+        assert_eq!(result.money, money(4500, "USD"));
+        assert_eq!(result.pricing, None); // Not on sale
+        assert!(result.image_url.ends_with("/hoodie.jpg")); // The variation has no image
+    }
+
+    #[test]
+    fn rescales_prices_to_the_currency_minor_units() {
+        // Stores can be configured with more decimals than the currency has
+        let mut beanie = product(include_str!("../../fixtures/woocommerce/product-34.json"));
+        beanie.prices.currency_minor_unit = 3;
+        beanie.prices.price = "18000".to_string();
+        beanie.prices.regular_price = "20000".to_string();
+        let result = request(34).get_product_result(&beanie, None).unwrap();
+        assert_eq!(result.money, money(1800, "USD"));
+        assert_eq!(list_price(&result), money(2000, "USD"));
+
+        // Zero-decimal currencies
+        beanie.prices.currency_code = "JPY".to_string();
+        beanie.prices.currency_minor_unit = 0;
+        beanie.prices.price = "1800".to_string();
+        beanie.prices.regular_price = "2000".to_string();
+        let result = request(34).get_product_result(&beanie, None).unwrap();
+        assert_eq!(result.money, money(1800, "JPY"));
+        assert_eq!(result.price, 1800.0);
+    }
+
+    #[test]
+    fn rejects_malformed_prices() {
+        let mut beanie = product(include_str!("../../fixtures/woocommerce/product-34.json"));
+        beanie.prices.price = "18.00".to_string();
+        let error = request(34).get_product_result(&beanie, None).unwrap_err();
+        assert!(matches!(error, ScraperError::PriceFormat { .. }));
+    }
+
+    #[test]
+    fn rejects_currency_minor_units_beyond_an_i64() {
+        for currency_minor_unit in ["19", "4294967295"] {
+            let json = include_str!("../../fixtures/woocommerce/product-34.json").replace(
+                r#""currency_minor_unit": 2"#,
+                &format!(r#""currency_minor_unit": {}"#, currency_minor_unit),
+            );
+            let error = request(34)
+                .get_product_result(&product(&json), None)
+                .unwrap_err();
+            assert!(matches!(error, ScraperError::PriceFormat { .. }));
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use reqwest::Client;
+use tokio::sync::{mpsc::Sender, Mutex};
+
+use crate::{errors::ScraperError, pubsub::get_access_token};
+
+/// Tokens are renewed once they are within this window of expiring.
+const REFRESH_MARGIN: Duration = Duration::from_secs(300);
+
+/// Delay before the background refresher retries after a failed refresh.
+const REFRESH_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+struct CachedToken {
+    access_token: String,
+    refresh_at: Instant,
+}
+
+/// Caches the access token issued by the metadata server and renews it before it expires.
+/// Clones are cheap and share the same cached token.
+#[derive(Clone)]
+pub(crate) struct TokenManager {
+    client: Client,
+    metadata_token_url: String,
+    cached_token: Arc<Mutex<Option<CachedToken>>>,
+}
+
+impl TokenManager {
+    pub(crate) fn new(client: Client, metadata_token_url: String) -> Self {
+        TokenManager {
+            client,
+            metadata_token_url,
+            cached_token: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Returns a valid access token, refreshing it first if it is close to expiring.
+    pub(crate) async fn get_access_token(&self) -> Result<String, ScraperError> {
+        let (access_token, _) = self.get_cached_token().await?;
+        Ok(access_token)
+    }
+
+    /// The lock is held for the duration of the refresh, so concurrent callers wait on the
+    /// single in-flight refresh instead of each querying the metadata server.
+    async fn get_cached_token(&self) -> Result<(String, Instant), ScraperError> {
+        let mut cached_token = self.cached_token.lock().await;
+
+        if let Some(token) = cached_token.as_ref() {
+            if Instant::now() < token.refresh_at {
+                return Ok((token.access_token.clone(), token.refresh_at));
+            }
+        }
+
+        // Refreshing the token
+        let auth_details = get_access_token(&self.client, &self.metadata_token_url).await?;
+        let expires_in = Duration::from_secs(auth_details.expires_in.max(0) as u64);
+        let refresh_at = Instant::now()
+            + expires_in
+                .saturating_sub(REFRESH_MARGIN)
+                .max(expires_in / 2);
+
+        let token = cached_token.insert(CachedToken {
+            access_token: auth_details.access_token,
+            refresh_at,
+        });
+
+        Ok((token.access_token.clone(), token.refresh_at))
+    }
+}
+
+/// Keeps the cached token fresh so that publishers rarely have to wait on a refresh.
+pub(crate) async fn spawn_token_refresh_service(
+    token_manager: TokenManager,
+    errors_tx: Sender<ScraperError>,
+) {
+    // Logging
+    println!("Starting up token refresh service.");
+
+    loop {
+        let sleep_duration = match token_manager.get_cached_token().await {
+            Ok((_, refresh_at)) => refresh_at.saturating_duration_since(Instant::now()),
+            Err(e) => {
+                errors_tx
+                    .send(e)
+                    .await
+                    .expect("Unexpected error when sending to the error channel.");
+                REFRESH_RETRY_DELAY
+            }
+        };
+
+        tokio::time::sleep(sleep_duration).await;
+    }
+}